@@ -3,6 +3,12 @@
 pub const CLOCK_FREQ:usize = 12500000; //时钟频率
 pub const MEMORY_END: usize = 0x8800_0000;
 
+/// goldfish RTC的寄存器基址，位于下方MMIO区域内
+pub const RTC_BASE: usize = 0x0010_1000;
+/// PLIC的寄存器基址
+pub const PLIC_BASE: usize = 0x0c00_0000;
+
 pub const MMIO: &[(usize, usize)] = &[
     (0x0010_0000, 0x00_2000), // VIRT_TEST/RTC  in virt machine
+    (0x0c00_0000, 0x40_0000), // PLIC
 ];
\ No newline at end of file
@@ -3,18 +3,63 @@
 pub const USER_STACK_SIZE: usize = 4096 * 2;
 pub const KERNEL_STACK_SIZE: usize = 4096 *2;
 pub const KERNEL_HEAP_SIZE: usize = 0x30_0000;
+/// 支持的最大hart数，需不小于qemu的-smp参数
+pub const MAX_HARTS: usize = 4;
 pub const PAGE_SIZE: usize = 0x1000;
 pub const PAGE_SIZE_BITS: usize = 0xc;
 
+/// 页表级数，Sv39为三级，Sv48为四级
+#[cfg(not(feature = "sv48"))]
+pub const PAGE_TABLE_LEVELS: usize = 3;
+/// 页表级数，Sv39为三级，Sv48为四级
+#[cfg(feature = "sv48")]
+pub const PAGE_TABLE_LEVELS: usize = 4;
+
+/// 跳板页位于虚拟地址空间的最高页，在Sv39和Sv48下都是符号扩展后合法的地址
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
 pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
 
-/// 返回内核空间中app对应的内核栈的栈顶地址和栈底地址
-pub fn kernel_stack_position(app_id: usize) -> (usize, usize) {
-    let top = TRAMPOLINE - app_id * (KERNEL_STACK_SIZE + PAGE_SIZE);
+/// 用户地址空间中mmap区域的基址（随机化之前）
+pub const MMAP_BASE: usize = 0x10_0000_0000;
+/// 位置无关可执行文件的加载基址（随机化之前）
+pub const PIE_BASE: usize = 0x1000_0000;
+/// ASLR随机偏移的最大页数，分别用于PIE加载基址、用户栈顶、堆底和mmap基址
+pub const ASLR_PIE_PAGES: usize = 0x1000;
+pub const ASLR_STACK_PAGES: usize = 0x100;
+pub const ASLR_HEAP_PAGES: usize = 0x100;
+pub const ASLR_MMAP_PAGES: usize = 0x1_0000;
+
+/// 关机策略
+#[derive(PartialEq, Eq)]
+pub enum ShutdownPolicy {
+    /// 所有任务都退出后关机
+    LastTaskExits,
+    /// 初始任务退出时立即关机，不再等待其余任务
+    InitExits,
+}
+pub const SHUTDOWN_POLICY: ShutdownPolicy = ShutdownPolicy::LastTaskExits;
+/// 初始任务的id，即第一个应用
+pub const INIT_TASK_ID: usize = 0;
+
+/// 返回内核空间中任务（线程）对应的内核栈的栈顶地址和栈底地址
+pub fn kernel_stack_position(task_id: usize) -> (usize, usize) {
+    let top = TRAMPOLINE - task_id * (KERNEL_STACK_SIZE + PAGE_SIZE);
     let bottom = top - KERNEL_STACK_SIZE;
     (bottom, top)
 }
 
+/// 返回线程tid的Trap上下文页的起始地址，主线程（tid为0）使用TRAP_CONTEXT
+pub fn trap_cx_bottom_from_tid(tid: usize) -> usize {
+    TRAP_CONTEXT - tid * PAGE_SIZE
+}
+
+/// 返回线程tid（tid >= 1）的用户栈的栈底地址和栈顶地址，
+/// 线程栈从mmap基址向下依次排列，相邻的栈之间留出一个保护页
+pub fn thread_stack_position(mmap_base: usize, tid: usize) -> (usize, usize) {
+    let top = mmap_base - (tid - 1) * (USER_STACK_SIZE + PAGE_SIZE) - PAGE_SIZE;
+    let bottom = top - USER_STACK_SIZE;
+    (bottom, top)
+}
+
 // 本操作系统采用qemu模拟器运行，此处记录qemu的时钟频率
-pub use crate::board::{CLOCK_FREQ, MEMORY_END, MMIO};
+pub use crate::board::{CLOCK_FREQ, MEMORY_END, MMIO, PLIC_BASE, RTC_BASE};
@@ -1,8 +1,13 @@
 //! 用于文本输出的控制台驱动
 
-use crate::sbi::console_putchar;
+use crate::config::INIT_TASK_ID;
+use crate::sbi::{console_getchar, console_putchar};
+use crate::sync::SpinNoIrqLock;
+use crate::task::{send_signal, SignalFlags};
 
+use alloc::vec::Vec;
 use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, Ordering};
 
 struct Stdout;
 
@@ -15,8 +20,71 @@ impl Write for Stdout {
     }
 }
 
+/// 多个hart同时打印时按整条消息互斥，避免输出交错；时钟中断处理中也会打印，因此持锁时关中断
+static STDOUT: SpinNoIrqLock<Stdout> = SpinNoIrqLock::new(Stdout);
+
 pub fn print(args: fmt::Arguments) {
-    Stdout.write_fmt(args).unwrap();
+    STDOUT.lock().write_fmt(args).unwrap();
+}
+
+/// 终端中断字符（Ctrl-C）
+const CTRL_C: usize = 0x03;
+
+/// 已经开始运行且尚未退出的非初始进程，按开始运行的先后排列。
+/// 最后一个是前台进程，Ctrl-C发给它；没有时发给初始进程
+static FOREGROUND: SpinNoIrqLock<Vec<usize>> = SpinNoIrqLock::new(Vec::new());
+/// 是否有hart正在轮询控制台输入，多个hart同时读串口可能重复或丢失字符
+static POLLING: AtomicBool = AtomicBool::new(false);
+/// 收到了Ctrl-C，但还没有向前台进程发送SIGINT
+static CTRL_C_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// 进程pid开始运行，成为前台进程
+pub fn push_foreground(pid: usize) {
+    if pid != INIT_TASK_ID {
+        FOREGROUND.lock().push(pid);
+    }
+}
+
+/// 进程pid退出，前台交还给在它之前开始运行的进程
+pub fn remove_foreground(pid: usize) {
+    FOREGROUND.lock().retain(|&foreground| foreground != pid);
+}
+
+/// 轮询控制台输入，收到Ctrl-C时只做记录，由[`deliver_ctrl_c`]发送SIGINT；
+/// 内核还不支持读取标准输入，其他字符直接丢弃。可以在中断处理中调用
+pub fn poll_ctrl_c() {
+    if POLLING
+        .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        return;
+    }
+    let mut ctrl_c = false;
+    loop {
+        match console_getchar() {
+            CTRL_C => ctrl_c = true,
+            // 没有输入时返回-1
+            usize::MAX => break,
+            _ => {}
+        }
+    }
+    POLLING.store(false, Ordering::Release);
+    if ctrl_c {
+        println!("^C");
+        CTRL_C_PENDING.store(true, Ordering::Release);
+    }
+}
+
+/// 把收到的Ctrl-C作为SIGINT发给前台进程。发送信号要获取进程的锁，
+/// 在中断处理中获取可能与被打断的代码死锁，因此只在返回用户态前和空闲循环中调用
+pub fn deliver_ctrl_c() {
+    if !CTRL_C_PENDING.swap(false, Ordering::Acquire) {
+        return;
+    }
+    let pid = FOREGROUND.lock().last().copied().unwrap_or(INIT_TASK_ID);
+    if send_signal(pid, SignalFlags::SIGINT) {
+        println!("[kernel] send SIGINT to process {}", pid);
+    }
 }
 
 /// 打印宏
@@ -1,15 +1,33 @@
 # os/src/entry.asm
+# 每个hart从SBI进入时a0为hart id，按hart id在启动栈区中选择自己的栈，
+# 并把hart id保存在tp中，内核通过tp区分当前运行在哪个hart上
     .section .text.entry
     .globl _start
 _start:
-    la sp, boot_stack_top # la load address
+    mv tp, a0
+    call set_boot_stack
     call rust_main
+
+    # 其余hart由启动hart通过SBI HSM扩展从这里启动，此时尚未开启分页
+    .globl _start_secondary
+_start_secondary:
+    mv tp, a0
+    call set_boot_stack
+    call rust_main_secondary
+
+# sp = boot_stack_top - hartid * 64KiB
+set_boot_stack:
+    la sp, boot_stack_top # la load address
+    slli t0, tp, 16
+    sub sp, sp, t0
+    ret
+
     .section .bss.stack
     .globl boot_stack_lower_bound
 boot_stack_lower_bound:
-    .space 4096 * 16
+    .space 4096 * 16 * 4
     .globl boot_stack_top
 boot_stack_top:
 # 没有声明boot_stack_top的值但是却能正确将栈顶地址加载到栈指针寄存器中
 # 是因为上述内存分配至boot_stack_lower_bound后已经分配完栈空间的大小
-# 在最后这个boot_stack_top栈顶地址的值自动计算确定
\ No newline at end of file
+# 在最后这个boot_stack_top栈顶地址的值自动计算确定
@@ -24,3 +24,26 @@ pub fn get_app_data(app_id: usize) -> &'static [u8] {
         )
     }
 }
+
+/// 获取用户程序名，由build.rs在`_app_names`处以'\0'分隔依次存放
+pub fn get_app_name(app_id: usize) -> &'static str {
+    extern "C" {
+        fn _app_names();
+    }
+    assert!(app_id < get_num_app());
+    let mut start = _app_names as usize as *const u8;
+    unsafe {
+        for _ in 0..app_id {
+            while start.read_volatile() != b'\0' {
+                start = start.add(1);
+            }
+            start = start.add(1);
+        }
+        let mut end = start;
+        while end.read_volatile() != b'\0' {
+            end = end.add(1);
+        }
+        let slice = core::slice::from_raw_parts(start, end as usize - start as usize);
+        core::str::from_utf8(slice).unwrap()
+    }
+}
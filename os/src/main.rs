@@ -36,6 +36,8 @@ mod config;
 pub mod task;
 mod loader;
 mod mm;
+mod plic;
+mod random;
 
 #[path = "board/qemu.rs"]
 mod board;
@@ -44,21 +46,55 @@ global_asm!(include_str!("entry.asm"));
 global_asm!(include_str!("link_app.S"));
 
 #[no_mangle]
-fn rust_main() -> ! {
+fn rust_main(hartid: usize) -> ! {
     logging::init();
     clear_bss();
     trace!("Hello, world!");
-    println!("[kernel] Hello, world!");
+    println!("[kernel] Hello, world! boot hart = {}", hartid);
     mm::init();
     println!("[kernel] back to world!");
     mm::remap_test();
+    task::mlfq_test();
+    timer::init();
     trap::init();
     trap::enable_timer_interrupt();
+    trap::enable_external_interrupt();
+    trap::enable_software_interrupt();
+    mm::hart_online();
     timer::set_next_trigger();
+    task::init();
+    start_secondary_harts(hartid);
     task::run_first_task();
     panic!("Unreachable in rust_main!");
 }
 
+/// 从_start_secondary进入的其余hart，共享启动hart建立好的内核地址空间和任务管理器
+#[no_mangle]
+fn rust_main_secondary(hartid: usize) -> ! {
+    mm::init_secondary();
+    trap::init();
+    trap::enable_timer_interrupt();
+    trap::enable_external_interrupt();
+    trap::enable_software_interrupt();
+    mm::hart_online();
+    timer::set_next_trigger();
+    println!("[kernel] hart {} started", hartid);
+    task::run_first_task();
+    panic!("Unreachable in rust_main_secondary!");
+}
+
+/// 通过SBI HSM扩展启动除启动hart以外的所有hart，不存在的hart会启动失败并被跳过
+fn start_secondary_harts(boot_hartid: usize) {
+    extern "C" {
+        fn _start_secondary();
+    }
+    for hartid in (0..config::MAX_HARTS).filter(|&id| id != boot_hartid) {
+        if !sbi::hart_start(hartid, _start_secondary as usize, 0) {
+            trace!("hart {} not started", hartid);
+        }
+    }
+}
+
 fn clear_bss() { //bss段清零函数
     extern "C" {
         fn sbss();
@@ -1,14 +1,25 @@
 //! Implementation of physical and virtual address and page number.
 
 use super::PageTableEntry;
-use crate::config::{PAGE_SIZE, PAGE_SIZE_BITS};
+use crate::config::{MEMORY_END, PAGE_SIZE, PAGE_SIZE_BITS, PAGE_TABLE_LEVELS, TRAMPOLINE, TRAP_CONTEXT};
 use core::fmt::{self, Debug, Formatter};
 
-/// 地址位宽
-const PA_WIDTH_SV39: usize = 56;
-const VA_WIDTH_SV39: usize = 39;
-const PPN_WIDTH_SV39: usize = PA_WIDTH_SV39 - PAGE_SIZE_BITS;
-const VPN_WIDTH_SV39: usize = VA_WIDTH_SV39 - PAGE_SIZE_BITS;
+/// 地址位宽，虚拟地址每多一级页表多9位
+const PA_WIDTH: usize = 56;
+const VA_WIDTH: usize = PAGE_SIZE_BITS + 9 * PAGE_TABLE_LEVELS;
+const PPN_WIDTH: usize = PA_WIDTH - PAGE_SIZE_BITS;
+const VPN_WIDTH: usize = VA_WIDTH - PAGE_SIZE_BITS;
+
+/// 判断地址在当前分页模式下是否为合法的符号扩展形式
+const fn is_canonical_va(va: usize) -> bool {
+    let high = va >> (VA_WIDTH - 1);
+    high == 0 || high == usize::MAX >> (VA_WIDTH - 1)
+}
+
+// 编译期检查各地址常量是否落在当前分页模式的位宽之内
+const _: () = assert!(VA_WIDTH == 39 || VA_WIDTH == 48, "unsupported paging mode");
+const _: () = assert!(is_canonical_va(TRAMPOLINE) && is_canonical_va(TRAP_CONTEXT));
+const _: () = assert!(MEMORY_END < 1 << PA_WIDTH);
 
 /// 物理地址
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
@@ -52,7 +63,7 @@ impl Debug for PhysPageNum {
 /// usize -> T: usize.into()
 impl From<usize> for PhysAddr {
     fn from(v: usize) -> Self {
-        Self(v & ((1 << PA_WIDTH_SV39) - 1))
+        Self(v & ((1 << PA_WIDTH) - 1))
     }
 }
 /* usize转换物理页面解释
@@ -60,17 +71,17 @@ impl From<usize> for PhysAddr {
  * 传入的就是物理页号，因此直接提取低44位即可 */
 impl From<usize> for PhysPageNum {
     fn from(v: usize) -> Self {
-        Self(v & ((1 << PPN_WIDTH_SV39) - 1))
+        Self(v & ((1 << PPN_WIDTH) - 1))
     }
 }
 impl From<usize> for VirtAddr {
     fn from(v: usize) -> Self {
-        Self(v & ((1 << VA_WIDTH_SV39) - 1))
+        Self(v & ((1 << VA_WIDTH) - 1))
     }
 }
 impl From<usize> for VirtPageNum {
     fn from(v: usize) -> Self {
-        Self(v & ((1 << VPN_WIDTH_SV39) - 1))
+        Self(v & ((1 << VPN_WIDTH) - 1))
     }
 }
 
@@ -89,8 +100,8 @@ impl From<PhysPageNum> for usize {
 impl From<VirtAddr> for usize {
     fn from(v: VirtAddr) -> Self {
         // 此处添加分支是为了判断虚拟地址的符号位，保存负数虚拟地址
-        if v.0 >= (1 << (VA_WIDTH_SV39 - 1)) {
-            v.0 | (!((1 << VA_WIDTH_SV39) - 1))
+        if v.0 >= (1 << (VA_WIDTH - 1)) {
+            v.0 | (!((1 << VA_WIDTH) - 1))
         } else {
             v.0
         }
@@ -162,6 +173,10 @@ impl PhysAddr {
     pub fn aligned(&self) -> bool {
         self.page_offset() == 0
     }
+    /// 获取一个放在该物理地址处对应类型为T的可变引用
+    pub fn get_mut<T>(&self) -> &'static mut T {
+        unsafe { (self.0 as *mut T).as_mut().unwrap() }
+    }
 }
 /// 从物理地址转化为物理页号
 impl From<PhysAddr> for PhysPageNum {
@@ -179,11 +194,11 @@ impl From<PhysPageNum> for PhysAddr {
 
 /// 获取虚拟页号的子信息
 impl VirtPageNum {
-    /// 获取虚拟页号三个等级的索引，返回结果中虚拟索引的级别从高到低
-    pub fn indexes(&self) -> [usize; 3] {
+    /// 获取虚拟页号各级页表的索引（Sv39三级，Sv48四级），返回结果中虚拟索引的级别从高到低
+    pub fn indexes(&self) -> [usize; PAGE_TABLE_LEVELS] {
         let mut vpn = self.0;
-        let mut idx = [0usize; 3];
-        for i in (0..3).rev() {
+        let mut idx = [0usize; PAGE_TABLE_LEVELS];
+        for i in (0..PAGE_TABLE_LEVELS).rev() {
             idx[i] = vpn & 511; //提取低9位，511 = 2^9 - 1
             vpn >>= 9;
         }
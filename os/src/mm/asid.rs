@@ -0,0 +1,151 @@
+//! ASID（地址空间标识符）分配器
+//!
+//! 每个用户地址空间分配一个ASID并写入satp，TLB表项会带上ASID标签，
+//! 因此切换地址空间时无需再用`sfence.vma`全局刷新TLB。
+//!
+//! 回收策略：
+//! 1. 地址空间销毁时，ASID在刷新所有hart上的TLB表项后放入回收站优先复用
+//! 2. ASID耗尽时进入新的一代（generation）并在所有hart上全局刷新一次TLB，
+//!    旧代的地址空间在下一次返回用户态前重新分配ASID。
+//!    换代时其他hart可能仍在用户态运行旧代的地址空间，继续留下旧ASID的表项，
+//!    因此每个hart在换代后第一次返回用户态前还要再全局刷新一次
+
+use super::tlb::{tlb_shootdown, FLUSH_ALL};
+use crate::sync::{irq_restore, irq_save, SpinLock};
+use crate::task::hart_id;
+use alloc::vec::Vec;
+use core::arch::asm;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use lazy_static::*;
+use riscv::register::satp;
+
+/// 内核地址空间固定使用的ASID，不参与分配
+pub const KERNEL_ASID: usize = 0;
+
+/// satp寄存器中ASID字段的偏移和掩码（RV64最多16位）
+pub const SATP_ASID_SHIFT: usize = 44;
+const SATP_ASID_MASK: usize = 0xffff;
+
+/// 换代之后还没有全局刷新过TLB的hart，第i位对应hart i
+static STALE_HARTS: AtomicUsize = AtomicUsize::new(0);
+
+/// 一个已分配的ASID，generation记录其所属的代
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Asid {
+    /// 写入satp的ASID值
+    pub value: usize,
+    /// 分配时所处的代
+    pub generation: usize,
+}
+
+/// ASID分配器
+struct AsidAllocator {
+    max_asid: usize,       // 硬件支持的最大ASID
+    current: usize,        // 本代中下一个从未分配过的ASID
+    generation: usize,     // 当前代数
+    recycled: Vec<usize>,  // 回收站
+}
+
+impl AsidAllocator {
+    fn new() -> Self {
+        Self {
+            max_asid: 0,
+            current: KERNEL_ASID + 1,
+            generation: 1,
+            recycled: Vec::new(),
+        }
+    }
+    fn alloc(&mut self) -> Asid {
+        // 优先复用回收站中的ASID，其TLB表项已在回收时刷新
+        let value = if let Some(value) = self.recycled.pop() {
+            value
+        } else if self.current <= self.max_asid {
+            self.current += 1;
+            self.current - 1
+        } else {
+            // ASID耗尽，进入新的一代，旧代的ASID全部作废
+            self.generation += 1;
+            self.recycled.clear();
+            self.current = KERNEL_ASID + 2;
+            STALE_HARTS.store(usize::MAX, Ordering::SeqCst);
+            tlb_shootdown(FLUSH_ALL);
+            KERNEL_ASID + 1
+        };
+        Asid {
+            value,
+            generation: self.generation,
+        }
+    }
+    fn dealloc(&mut self, asid: Asid) {
+        // 旧代的ASID在换代时已经作废，无需回收
+        if asid.generation != self.generation {
+            return;
+        }
+        // 其他hart上也可能残留该ASID的表项
+        tlb_shootdown(asid.value);
+        self.recycled.push(asid.value);
+    }
+}
+
+lazy_static! {
+    /// 全局ASID分配器
+    static ref ASID_ALLOCATOR: SpinLock<AsidAllocator> =
+        SpinLock::new(AsidAllocator::new());
+}
+
+/// 探测硬件实现的ASID位数并初始化分配器，需在内核地址空间激活后调用
+pub fn init_asid_allocator() {
+    // 向satp的ASID字段写入全1，硬件只会保留其实现的位
+    let old = satp::read().bits();
+    let max_asid = unsafe {
+        satp::write(old | (SATP_ASID_MASK << SATP_ASID_SHIFT));
+        let probed = (satp::read().bits() >> SATP_ASID_SHIFT) & SATP_ASID_MASK;
+        satp::write(old);
+        probed
+    };
+    flush_tlb_all();
+    // 跳板页中切换satp时不再刷新TLB，因此必须依赖硬件ASID
+    assert!(max_asid > KERNEL_ASID, "satp.ASID is not implemented on this hart!");
+    println!("[kernel] ASID bits probed, max asid = {:#x}", max_asid);
+    ASID_ALLOCATOR.lock().max_asid = max_asid;
+}
+
+/// 分配一个ASID
+pub fn asid_alloc() -> Asid {
+    ASID_ALLOCATOR.lock().alloc()
+}
+
+/// 回收一个ASID
+pub fn asid_dealloc(asid: Asid) {
+    ASID_ALLOCATOR.lock().dealloc(asid);
+}
+
+/// 判断ASID是否属于当前代（即仍然有效）
+pub fn asid_is_current(asid: Asid) -> bool {
+    asid.generation == ASID_ALLOCATOR.lock().generation
+}
+
+/// 换代后本hart第一次调用时全局刷新TLB，需在写入satp返回用户态之前调用
+pub fn flush_after_rollover() {
+    // 读取hart id和刷新之间不能被抢占迁移
+    let sie = irq_save();
+    let mask = 1 << hart_id();
+    if STALE_HARTS.fetch_and(!mask, Ordering::SeqCst) & mask != 0 {
+        flush_tlb_all();
+    }
+    irq_restore(sie);
+}
+
+/// 只刷新带有指定ASID标签的TLB表项
+pub fn flush_tlb_asid(asid: usize) {
+    unsafe {
+        asm!("sfence.vma zero, {}", in(reg) asid);
+    }
+}
+
+/// 刷新全部TLB表项
+pub fn flush_tlb_all() {
+    unsafe {
+        asm!("sfence.vma");
+    }
+}
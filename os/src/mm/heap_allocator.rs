@@ -1,11 +1,27 @@
 //! 全局内存分配器
 
 use crate::config::KERNEL_HEAP_SIZE;
+use crate::sync::IrqGuard;
 use buddy_system_allocator::LockedHeap;
+use core::alloc::{GlobalAlloc, Layout};
+
+/// 关中断的堆分配器：时钟中断处理中也会分配和释放内存，持有堆的锁时不能被中断
+struct NoIrqHeap(LockedHeap);
+
+unsafe impl GlobalAlloc for NoIrqHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _irq = IrqGuard::new();
+        self.0.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let _irq = IrqGuard::new();
+        self.0.dealloc(ptr, layout)
+    }
+}
 
 /// 堆分配器的实例
 #[global_allocator]
-static HEAP_ALLOCATOR: LockedHeap = LockedHeap::empty();
+static HEAP_ALLOCATOR: NoIrqHeap = NoIrqHeap(LockedHeap::empty());
 
 /// 内存分配失败的处理函数
 #[alloc_error_handler]
@@ -20,6 +36,7 @@ static mut HEAP_SPACE: [u8; KERNEL_HEAP_SIZE] = [0; KERNEL_HEAP_SIZE];
 pub fn init_heap() {
     unsafe {
         HEAP_ALLOCATOR
+            .0
             .lock() // 注意此处要获取堆分配器的锁，其被Mutex保护
             .init(HEAP_SPACE.as_ptr() as usize, KERNEL_HEAP_SIZE);
     }
@@ -1,15 +1,23 @@
 //! Implementation of [`MapArea`] and [`MemorySet`].
 
+use super::asid::{
+    asid_alloc, asid_dealloc, asid_is_current, flush_after_rollover, flush_tlb_asid, Asid,
+    KERNEL_ASID,
+};
+use super::tlb::tlb_shootdown;
 use super::{frame_alloc, FrameTracker};
-use super::{PTEFlags, PageTable, PageTableEntry};
+use super::{PTEFlags, PageSize, PageTable, PageTableEntry};
 use super::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
 use super::{StepByOne, VPNRange};
-use crate::config::{MEMORY_END, MMIO, PAGE_SIZE, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE};
-use crate::sync::UPSafeCell;
+use crate::config::{
+    ASLR_HEAP_PAGES, ASLR_MMAP_PAGES, ASLR_PIE_PAGES, ASLR_STACK_PAGES, MEMORY_END, MMAP_BASE,
+    MMIO, PAGE_SIZE, PIE_BASE, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE,
+};
+use crate::sync::{SpinNoIrqLock, RANK_KERNEL_SPACE};
 use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
-use core::arch::asm;
 use lazy_static::*;
 use riscv::register::satp;
 
@@ -26,16 +34,45 @@ extern "C" {
     fn strampoline();
 }
 
+/// 辅助向量（auxv）中的类型
+pub const AT_NULL: usize = 0;
+const AT_PHDR: usize = 3;
+const AT_PHENT: usize = 4;
+const AT_PHNUM: usize = 5;
+const AT_PAGESZ: usize = 6;
+const AT_BASE: usize = 7;
+const AT_ENTRY: usize = 9;
+
+/// RISC-V重定位类型
+const R_RISCV_NONE: u32 = 0;
+const R_RISCV_RELATIVE: u32 = 3;
+
 lazy_static! {
     /// 实例化一个memory_set数据结构的内核地址空间
-    pub static ref KERNEL_SPACE: Arc<UPSafeCell<MemorySet>> =
-        Arc::new(unsafe { UPSafeCell::new(MemorySet::new_kernel()) });
+    pub static ref KERNEL_SPACE: Arc<SpinNoIrqLock<MemorySet>> = Arc::new(
+        SpinNoIrqLock::new_ranked(MemorySet::new_kernel(), RANK_KERNEL_SPACE)
+    );
 }
 
 /// 虚拟地址空间数据结构
 pub struct MemorySet {
     page_table: PageTable,
     areas: Vec<MapArea>,
+    /// 用户地址空间的ASID，None表示内核地址空间（固定使用KERNEL_ASID）
+    asid: Option<Asid>,
+    /// mmap区域的基址
+    mmap_base: usize,
+}
+
+/// 生成一个随机的页对齐偏移，范围为[0, max_pages)页；关闭ASLR时恒为0
+#[cfg(not(feature = "no_aslr"))]
+fn aslr_offset(max_pages: usize) -> usize {
+    (crate::random::rand_usize() % max_pages) * PAGE_SIZE
+}
+/// 生成一个随机的页对齐偏移，范围为[0, max_pages)页；关闭ASLR时恒为0
+#[cfg(feature = "no_aslr")]
+fn aslr_offset(_max_pages: usize) -> usize {
+    0
 }
 
 impl MemorySet {
@@ -43,11 +80,37 @@ impl MemorySet {
         Self {
             page_table: PageTable::new(),
             areas: Vec::new(),
+            asid: None,
+            mmap_base: MMAP_BASE,
         }
     }
-    /// 构造当前地址空间的token
+    /// 获取当前地址空间使用的ASID值
+    fn asid_value(&self) -> usize {
+        self.asid.map_or(KERNEL_ASID, |asid| asid.value)
+    }
+    /// 构造当前地址空间的token（带ASID标签）
     pub fn token(&self) -> usize {
-        self.page_table.token()
+        self.page_table.token(self.asid_value())
+    }
+    /// 若ASID因换代而失效，则重新分配一个，需在写入satp之前调用
+    pub fn refresh_asid(&mut self) {
+        // 换代前本hart上运行的旧代地址空间可能留下了与新分配的ASID值相同的表项；
+        // 先刷新再检查代数，检查之后才发生的换代会留到下一次返回用户态前刷新
+        flush_after_rollover();
+        if let Some(asid) = self.asid {
+            if !asid_is_current(asid) {
+                self.asid = Some(asid_alloc());
+            }
+        }
+    }
+    /// 获取mmap区域的基址
+    pub fn mmap_base(&self) -> usize {
+        self.mmap_base
+    }
+    /// 刷新所有hart的TLB中属于当前地址空间的表项：用户地址空间可能被多个hart上的线程同时使用，
+    /// 内核地址空间中被回收的内核栈可能还留在其他hart的TLB中
+    fn flush_tlb(&self) {
+        tlb_shootdown(self.asid_value());
     }
     /// 在当前地址空间中插入一个逻辑段
     pub fn insert_framed_area(
@@ -60,6 +123,21 @@ impl MemorySet {
             MapArea::new(start_va, end_va, MapType::Framed, permission),
             None,
         );
+        self.flush_tlb();
+    }
+    /// 移除起始虚拟页号为start_vpn的逻辑段并解除其映射
+    pub fn remove_area_with_start_vpn(&mut self, start_vpn: VirtPageNum) {
+        if let Some(idx) = self
+            .areas
+            .iter()
+            .position(|area| area.vpn_range.get_start() == start_vpn)
+        {
+            let mut area = self.areas.remove(idx);
+            let frames = area.unmap(&mut self.page_table);
+            // 其他hart确认刷新之前仍可能通过TLB访问这些物理页帧，之后才能回收
+            self.flush_tlb();
+            drop(frames);
+        }
     }
     /// 在当前地址空间中插入一个逻辑段，并将data写入该逻辑段（若有意义）
     fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
@@ -156,9 +234,12 @@ impl MemorySet {
         memory_set
     }
     /// 映射elf中的必要段，以及跳板页,TrapContext,用户栈
-    /// 返回地址空间，用户栈顶指针和程序入口地址
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
+    /// 位置无关的可执行文件（ET_DYN）会被加载到选定的基址并完成R_RISCV_RELATIVE重定位
+    /// 用户栈顶、堆底和mmap基址在开启ASLR时会加上随机偏移
+    /// 返回地址空间，用户栈顶指针，堆底地址，程序入口地址和辅助向量
+    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize, usize, Vec<(usize, usize)>) {
         let mut memory_set = Self::new_bare();
+        memory_set.asid = Some(asid_alloc());
         // map trampoline
         memory_set.map_trampoline();
         // map program headers of elf, with U flag
@@ -166,15 +247,26 @@ impl MemorySet {
         let elf_header = elf.header;
         let magic = elf_header.pt1.magic;
         assert_eq!(magic, [0x7f, 0x45, 0x4c, 0x46], "invalid elf!");
+        // 位置无关的可执行文件需要选择一个加载基址，固定地址的可执行文件基址为0
+        let base = match elf_header.pt2.type_().as_type() {
+            xmas_elf::header::Type::SharedObject => PIE_BASE + aslr_offset(ASLR_PIE_PAGES),
+            xmas_elf::header::Type::Executable => 0,
+            _ => panic!("unsupported elf type!"),
+        };
         // 获取programmer header的数量
         let ph_count = elf_header.pt2.ph_count();
         let mut max_end_vpn = VirtPageNum(0);
+        let mut phdr_va = 0;
         for i in 0..ph_count {
             let ph = elf.program_header(i).unwrap();
             if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
                 // 获取一个program header的起始/终止虚拟地址
-                let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
-                let end_va: VirtAddr = ((ph.virtual_addr() + ph.mem_size()) as usize).into();
+                let start_va: VirtAddr = (base + ph.virtual_addr() as usize).into();
+                let end_va: VirtAddr = (base + (ph.virtual_addr() + ph.mem_size()) as usize).into();
+                // 覆盖了ELF头的段同样包含程序头表，据此得到程序头表在内存中的地址
+                if ph.offset() == 0 {
+                    phdr_va = base + ph.virtual_addr() as usize + elf_header.pt2.ph_offset() as usize;
+                }
                 // 为应用程序赋予用户级访问权限
                 let mut map_perm = MapPermission::U;
                 let ph_flags = ph.flags();
@@ -196,12 +288,27 @@ impl MemorySet {
                 );
             }
         }
+        if base != 0 {
+            memory_set.relocate(&elf, base);
+        }
+        let entry_point = base + elf_header.pt2.entry_point() as usize;
+        // 传递给用户程序的辅助向量
+        let auxv = vec![
+            (AT_PHDR, phdr_va),
+            (AT_PHENT, elf_header.pt2.ph_entry_size() as usize),
+            (AT_PHNUM, ph_count as usize),
+            (AT_PAGESZ, PAGE_SIZE),
+            (AT_BASE, 0),
+            (AT_ENTRY, entry_point),
+        ];
         // 映射用户栈并赋予用户级访问权限
         let max_end_va: VirtAddr = max_end_vpn.into();
         let mut user_stack_bottom: usize = max_end_va.into();
-        // 保护页
-        user_stack_bottom += PAGE_SIZE;
+        // 保护页，之后加上随机偏移
+        user_stack_bottom += PAGE_SIZE + aslr_offset(ASLR_STACK_PAGES);
         let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
+        // 堆与用户栈之间同样留出保护页和随机间隔
+        let heap_bottom = user_stack_top + PAGE_SIZE + aslr_offset(ASLR_HEAP_PAGES);
         memory_set.push(
             MapArea::new(
                 user_stack_bottom.into(),
@@ -214,8 +321,8 @@ impl MemorySet {
         // 待使用sbrk系统调用
         memory_set.push(
             MapArea::new(
-                user_stack_top.into(),
-                user_stack_top.into(),
+                heap_bottom.into(),
+                heap_bottom.into(),
                 MapType::Framed,
                 MapPermission::R | MapPermission::W | MapPermission::U,
             ),
@@ -231,19 +338,46 @@ impl MemorySet {
             ),
             None,
         );
+        memory_set.mmap_base = MMAP_BASE - aslr_offset(ASLR_MMAP_PAGES);
         (
             memory_set,
             user_stack_top,
-            elf.header.pt2.entry_point() as usize, // 应用从程序入口地址
+            heap_bottom,
+            entry_point, // 应用从程序入口地址
+            auxv,
         )
     }
-    /// 激活当前地址空间（装载sapt寄存器）
+    /// 对加载到base处的位置无关可执行文件进行重定位
+    /// 静态链接的PIE只包含R_RISCV_RELATIVE类型的重定位项：*(base + offset) = base + addend
+    fn relocate(&mut self, elf: &xmas_elf::ElfFile, base: usize) {
+        use xmas_elf::sections::{SectionData, ShType};
+        for section in elf.section_iter() {
+            if !matches!(section.get_type(), Ok(ShType::Rela)) {
+                continue;
+            }
+            if let Ok(SectionData::Rela64(relas)) = section.get_data(elf) {
+                for rela in relas {
+                    match rela.get_type() {
+                        R_RISCV_NONE => {}
+                        R_RISCV_RELATIVE => {
+                            let va = VirtAddr::from(base + rela.get_offset() as usize);
+                            let ppn = self.translate(va.floor()).unwrap().ppn();
+                            let pa = PhysAddr(PhysAddr::from(ppn).0 + va.page_offset());
+                            *pa.get_mut::<usize>() = base + rela.get_addend() as usize;
+                        }
+                        t => panic!("unsupported relocation type {} in PIE!", t),
+                    }
+                }
+            }
+        }
+    }
+    /// 激活当前地址空间（装载sapt寄存器），只刷新本地址空间ASID对应的TLB表项
     pub fn activate(&self) {
-        let satp = self.page_table.token();
+        let satp = self.token();
         unsafe {
             satp::write(satp);
-            asm!("sfence.vma");
         }
+        flush_tlb_asid(self.asid_value());
     }
     /// 翻译当前地址空间中的虚拟地址
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
@@ -257,7 +391,10 @@ impl MemorySet {
             .iter_mut()
             .find(|area| area.vpn_range.get_start() == start.floor())
         {
-            area.shrink_to(&mut self.page_table, new_end.ceil());
+            let frames = area.shrink_to(&mut self.page_table, new_end.ceil());
+            // 解除映射后需要刷新TLB中残留的旧表项，之后才能回收物理页帧
+            self.flush_tlb();
+            drop(frames);
             true
         } else {
             false
@@ -279,6 +416,14 @@ impl MemorySet {
     }
 }
 
+impl Drop for MemorySet {
+    fn drop(&mut self) {
+        if let Some(asid) = self.asid {
+            asid_dealloc(asid);
+        }
+    }
+}
+
 /// 段地址数据结构，用于控制一段连续虚拟内存
 pub struct MapArea {
     vpn_range: VPNRange,                                // SimpleRange<VirtPageNum> 虚拟页号范围
@@ -321,33 +466,87 @@ impl MapArea {
         page_table.map(vpn, ppn, pte_flags);
     }
     /// 在当前逻辑段中解除一对映射，并在相应的page_table页表中解除映射
+    /// 返回被解除映射的物理页帧，调用者需在刷新所有hart的TLB之后再释放它
     #[allow(unused)]
-    pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
+    #[must_use]
+    pub fn unmap_one(
+        &mut self,
+        page_table: &mut PageTable,
+        vpn: VirtPageNum,
+    ) -> Option<FrameTracker> {
+        page_table.unmap(vpn);
         if self.map_type == MapType::Framed {
-            self.data_frames.remove(&vpn);
+            self.data_frames.remove(&vpn)
+        } else {
+            None
         }
-        page_table.unmap(vpn);
+    }
+    /// 恒等映射时，从vpn开始可以使用的最大页面大小（需要按该大小对齐且不超出逻辑段）
+    fn identical_page_size(&self, vpn: VirtPageNum) -> PageSize {
+        let end = self.vpn_range.get_end().0;
+        *PageSize::ALL_DESC
+            .iter()
+            .find(|size| vpn.0 % size.pages() == 0 && vpn.0 + size.pages() <= end)
+            .unwrap()
     }
     /// 将当前逻辑段中的所有虚拟页号完成映射，并记录在page_table页表中
+    /// 恒等映射的逻辑段尽可能使用大页，以减少页表占用的物理页帧和TLB压力
     pub fn map(&mut self, page_table: &mut PageTable) {
-        for vpn in self.vpn_range {
-            self.map_one(page_table, vpn);
+        match self.map_type {
+            MapType::Identical => {
+                let pte_flags = PTEFlags::from_bits(self.map_perm.bits()).unwrap();
+                let mut vpn = self.vpn_range.get_start();
+                while vpn < self.vpn_range.get_end() {
+                    let size = self.identical_page_size(vpn);
+                    page_table.map_sized(vpn, PhysPageNum(vpn.0), pte_flags, size);
+                    vpn = VirtPageNum(vpn.0 + size.pages());
+                }
+            }
+            MapType::Framed => {
+                for vpn in self.vpn_range {
+                    self.map_one(page_table, vpn);
+                }
+            }
         }
     }
     /// 将当前逻辑段中的所有虚拟页号解除映射，并在相应的page_table页表中解除映射
+    /// 返回被解除映射的物理页帧，调用者需在刷新所有hart的TLB之后再释放它们
     #[allow(unused)]
-    pub fn unmap(&mut self, page_table: &mut PageTable) {
-        for vpn in self.vpn_range {
-            self.unmap_one(page_table, vpn);
+    #[must_use]
+    pub fn unmap(&mut self, page_table: &mut PageTable) -> Vec<FrameTracker> {
+        let mut frames = Vec::new();
+        match self.map_type {
+            MapType::Identical => {
+                let mut vpn = self.vpn_range.get_start();
+                while vpn < self.vpn_range.get_end() {
+                    let size = self.identical_page_size(vpn);
+                    page_table.unmap(vpn);
+                    vpn = VirtPageNum(vpn.0 + size.pages());
+                }
+            }
+            MapType::Framed => {
+                for vpn in self.vpn_range {
+                    frames.extend(self.unmap_one(page_table, vpn));
+                }
+            }
         }
+        frames
     }
     /// 将当前逻辑段中的映射缩小到新的结束虚拟页号（从后部减少）
+    /// 返回被解除映射的物理页帧，调用者需在刷新所有hart的TLB之后再释放它们
     #[allow(unused)]
-    pub fn shrink_to(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) {
-        for vpn in VPNRange::new(new_end, self.vpn_range.get_end()) {
-            self.unmap_one(page_table, vpn)
-        }
+    #[must_use]
+    pub fn shrink_to(
+        &mut self,
+        page_table: &mut PageTable,
+        new_end: VirtPageNum,
+    ) -> Vec<FrameTracker> {
+        let frames = VPNRange::new(new_end, self.vpn_range.get_end())
+            .into_iter()
+            .filter_map(|vpn| self.unmap_one(page_table, vpn))
+            .collect();
         self.vpn_range = VPNRange::new(self.vpn_range.get_start(), new_end);
+        frames
     }
     /// 将当前逻辑段中的映射扩展到新的结束虚拟页号（从后部增加）
     #[allow(unused)]
@@ -401,27 +600,27 @@ bitflags! {
 /// 验证内核多级页表已经设置成功的测试函数
 #[allow(unused)]
 pub fn remap_test() {
-    let mut kernel_space = KERNEL_SPACE.exclusive_access();
+    let kernel_space = KERNEL_SPACE.lock();
     let mid_text: VirtAddr = ((stext as usize + etext as usize) / 2).into();
     let mid_rodata: VirtAddr = ((srodata as usize + erodata as usize) / 2).into();
     let mid_data: VirtAddr = ((sdata as usize + edata as usize) / 2).into();
-    // 验证text代码段不可写
-    assert!(!kernel_space
-        .page_table
-        .translate(mid_text.floor())
-        .unwrap()
-        .writable(),);
-    // 验证rodata只读数据段不可写
-    assert!(!kernel_space
-        .page_table
-        .translate(mid_rodata.floor())
-        .unwrap()
-        .writable(),);
-    // 验证data数据段不可执行
-    assert!(!kernel_space
-        .page_table
-        .translate(mid_data.floor())
-        .unwrap()
-        .executable(),);
+    let mid_phys: VirtAddr = ((ekernel as usize + MEMORY_END) / 2).into();
+    // 验证text代码段可读可执行但不可写
+    let text_pte = kernel_space.page_table.translate(mid_text.floor()).unwrap();
+    assert!(text_pte.readable() && text_pte.executable() && !text_pte.writable());
+    // 验证rodata只读数据段只读
+    let rodata_pte = kernel_space.page_table.translate(mid_rodata.floor()).unwrap();
+    assert!(rodata_pte.readable() && !rodata_pte.writable() && !rodata_pte.executable());
+    // 验证data数据段可读写但不可执行
+    let data_pte = kernel_space.page_table.translate(mid_data.floor()).unwrap();
+    assert!(data_pte.readable() && data_pte.writable() && !data_pte.executable());
+    // 验证物理内存恒等映射使用了大页，且权限、翻译结果与4KiB映射一致
+    assert_ne!(
+        kernel_space.page_table.page_size(mid_phys.floor()),
+        Some(PageSize::Size4K)
+    );
+    let phys_pte = kernel_space.page_table.translate(mid_phys.floor()).unwrap();
+    assert!(phys_pte.readable() && phys_pte.writable() && !phys_pte.executable());
+    assert_eq!(phys_pte.ppn().0, mid_phys.floor().0);
     println!("remap_test passed!");
 }
@@ -1,10 +1,18 @@
 //! Implementation of [`PageTableEntry`] and [`PageTable`].
 
-use super::{frame_alloc, FrameTracker, PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
+use super::asid::SATP_ASID_SHIFT;
+use super::{frame_alloc, FrameTracker, PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
+use crate::config::PAGE_TABLE_LEVELS;
 use alloc::vec;
 use alloc::vec::Vec;
 use bitflags::*;
 
+/// satp寄存器中的分页模式：8为Sv39，9为Sv48
+#[cfg(not(feature = "sv48"))]
+const SATP_MODE: usize = 8;
+#[cfg(feature = "sv48")]
+const SATP_MODE: usize = 9;
+
 bitflags! {
     /// 定义页表项中的8位权限位
     #[derive(PartialEq)]
@@ -61,6 +69,52 @@ impl PageTableEntry {
     pub fn executable(&self) -> bool {
         (self.flags() & PTEFlags::X) != PTEFlags::empty()
     }
+    /// 判断该页表项是否为叶子页表项（R/W/X任一位被设置），否则指向下一级页表
+    pub fn is_leaf(&self) -> bool {
+        self.is_valid() && (self.flags() & (PTEFlags::R | PTEFlags::W | PTEFlags::X)) != PTEFlags::empty()
+    }
+}
+
+/// 叶子页表项映射的页面大小
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PageSize {
+    /// 4KiB普通页，叶子位于最后一级页表
+    Size4K,
+    /// 2MiB大页，叶子位于倒数第二级页表
+    Size2M,
+    /// 1GiB巨页，叶子位于倒数第三级页表（Sv39下即根页表）
+    Size1G,
+}
+
+impl PageSize {
+    /// 从大到小排列的所有页面大小
+    pub const ALL_DESC: [PageSize; 3] = [PageSize::Size1G, PageSize::Size2M, PageSize::Size4K];
+    /// 该页面大小对应多少个4KiB页
+    pub fn pages(&self) -> usize {
+        1 << (9 * self.order())
+    }
+    /// 叶子页表项距离最后一级页表的级数
+    fn order(&self) -> usize {
+        match self {
+            PageSize::Size4K => 0,
+            PageSize::Size2M => 1,
+            PageSize::Size1G => 2,
+        }
+    }
+    /// 叶子页表项所在的页表级别（根页表为第0级）
+    fn leaf_level(&self) -> usize {
+        PAGE_TABLE_LEVELS - 1 - self.order()
+    }
+    /// 根据叶子页表项所在的级别得到页面大小
+    fn from_leaf_level(level: usize) -> Self {
+        match PAGE_TABLE_LEVELS - 1 - level {
+            0 => PageSize::Size4K,
+            1 => PageSize::Size2M,
+            2 => PageSize::Size1G,
+            // 内核从不建立Sv48根页表中的512GiB叶子，出现时说明页表已损坏
+            order => unreachable!("unsupported leaf page table entry of order {}", order),
+        }
+    }
 }
 
 /// 页表结构
@@ -89,14 +143,14 @@ impl PageTable {
             frames: Vec::new(),
         }
     }
-    /// 根据虚拟页号查询三级页表项地址，并在不合法的一级/二级页表项下创建新的页，但不会创建三级页表项
-    fn find_pte_create(&mut self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
+    /// 根据虚拟页号查询第level级（根页表为第0级）页表项地址，并在不合法的中间页表项下创建新的页，但不会创建叶子页表项
+    fn find_pte_create(&mut self, vpn: VirtPageNum, level: usize) -> Option<&mut PageTableEntry> {
         let idxs = vpn.indexes();
         let mut ppn = self.root_ppn;
         let mut result: Option<&mut PageTableEntry> = None;
         for (i, idx) in idxs.iter().enumerate() {
             let pte = &mut ppn.get_pte_array()[*idx];
-            if i == 2 {
+            if i == level {
                 result = Some(pte);
                 break;
             }
@@ -105,19 +159,23 @@ impl PageTable {
                 *pte = PageTableEntry::new(frame.ppn, PTEFlags::V);
                 self.frames.push(frame);
             }
+            // 中间级已经存在大页映射，不能再向下建立页表
+            assert!(!pte.is_leaf(), "vpn {:?} is covered by a huge page", vpn);
             ppn = pte.ppn();
         }
         result
     }
-    /// 根据虚拟页号查询页表项地址，中途的无效页表项直接返回None
-    fn find_pte(&self, vpn: VirtPageNum) -> Option<&mut PageTableEntry> {
+    /// 根据虚拟页号查询叶子页表项地址及其所在级别，中途的无效页表项直接返回None
+    /// 遇到中间级的叶子页表项（大页）时提前返回
+    fn find_pte(&self, vpn: VirtPageNum) -> Option<(&mut PageTableEntry, usize)> {
         let idxs = vpn.indexes();
+        let last = idxs.len() - 1;
         let mut ppn = self.root_ppn;
-        let mut result: Option<&mut PageTableEntry> = None;
+        let mut result: Option<(&mut PageTableEntry, usize)> = None;
         for (i, idx) in idxs.iter().enumerate() {
             let pte = &mut ppn.get_pte_array()[*idx];
-            if i == 2 {
-                result = Some(pte);
+            if i == last || pte.is_leaf() {
+                result = Some((pte, i));
                 break;
             }
             if !pte.is_valid() {
@@ -130,24 +188,56 @@ impl PageTable {
     /// 构造一个虚拟页和物理页的映射，需要用到`物理页号`,`虚拟页号`和`权限位
     #[allow(unused)]
     pub fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) {
-        let pte = self.find_pte_create(vpn).unwrap();
+        self.map_sized(vpn, ppn, flags, PageSize::Size4K);
+    }
+    /// 构造一个指定大小的页映射，虚拟页号和物理页号都必须按该页面大小对齐
+    pub fn map_sized(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags, size: PageSize) {
+        assert!(
+            vpn.0 % size.pages() == 0 && ppn.0 % size.pages() == 0,
+            "vpn {:?} or ppn {:?} is not aligned to {:?}",
+            vpn,
+            ppn,
+            size
+        );
+        let pte = self.find_pte_create(vpn, size.leaf_level()).unwrap();
         assert!(!pte.is_valid(), "vpn {:?} is mapped before mapping", vpn);
         *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
     }
-    /// 解除一个虚拟页的映射，需要用到`虚拟页号`
+    /// 解除一个虚拟页的映射，需要用到`虚拟页号`，若该页属于大页则解除整个大页的映射
     #[allow(unused)]
     pub fn unmap(&mut self, vpn: VirtPageNum) {
-        let pte = self.find_pte(vpn).unwrap();
+        let (pte, _) = self.find_pte(vpn).unwrap();
         assert!(pte.is_valid(), "vpn {:?} is invalid before unmapping", vpn);
         *pte = PageTableEntry::empty();
     }
-    /// 获取三级页表项的副本，或者找不到直接返回None
+    /// 获取覆盖该虚拟页号的叶子页表项的副本，或者找不到直接返回None
+    /// 若叶子页表项为大页，则返回的物理页号已加上该虚拟页在大页内的偏移
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
-        self.find_pte(vpn).map(|pte| *pte)
+        self.find_pte(vpn).map(|(pte, level)| {
+            let pages = PageSize::from_leaf_level(level).pages();
+            if pages == 1 {
+                *pte
+            } else {
+                PageTableEntry::new(PhysPageNum(pte.ppn().0 + (vpn.0 & (pages - 1))), pte.flags())
+            }
+        })
+    }
+    /// 翻译虚拟地址，得到对应的物理地址
+    pub fn translate_va(&self, va: VirtAddr) -> Option<PhysAddr> {
+        self.translate(va.floor()).map(|pte| {
+            let aligned_pa: PhysAddr = pte.ppn().into();
+            PhysAddr(aligned_pa.0 + va.page_offset())
+        })
     }
-    /// 获取一种MODE为SV39模式的内存映射方式，同时低位设置为根页表的PPN，ASID为0
-    pub fn token(&self) -> usize {
-        8usize << 60 | self.root_ppn.0
+    /// 获取覆盖该虚拟页号的有效映射的页面大小
+    pub fn page_size(&self, vpn: VirtPageNum) -> Option<PageSize> {
+        self.find_pte(vpn)
+            .filter(|(pte, _)| pte.is_valid())
+            .map(|(_, level)| PageSize::from_leaf_level(level))
+    }
+    /// 获取一种MODE为SV39/SV48模式的内存映射方式，同时低位设置为根页表的PPN，中间设置为地址空间的ASID
+    pub fn token(&self, asid: usize) -> usize {
+        SATP_MODE << 60 | asid << SATP_ASID_SHIFT | self.root_ppn.0
     }
 }
 
@@ -173,3 +263,73 @@ pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&
     }
     v
 }
+
+/// 获取用户地址空间中ptr指向的类型为T的可变引用，假设该对象不跨越页边界
+pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> &'static mut T {
+    let page_table = PageTable::from_token(token);
+    let va = ptr as usize;
+    page_table
+        .translate_va(VirtAddr::from(va))
+        .unwrap()
+        .get_mut()
+}
+
+/// 把用户地址空间中的虚拟地址va翻译为物理地址，va所在页必须是用户可读的有效页
+pub fn translated_user_pa(token: usize, va: usize) -> Option<PhysAddr> {
+    let page_table = PageTable::from_token(token);
+    let va = VirtAddr::from(va);
+    page_table
+        .translate(va.floor())
+        .filter(|pte| pte.is_valid() && pte.readable() && pte.flags().contains(PTEFlags::U))
+        .map(|pte| PhysAddr(PhysAddr::from(pte.ppn()).0 + va.page_offset()))
+}
+
+/// 把内核中的src按字节复制到用户地址空间的dst处，允许跨越页边界。
+/// 目标区域中任一页未映射、或不是用户可写页时不做任何复制，返回false
+pub fn copy_to_user<T>(token: usize, dst: *mut T, src: &T) -> bool {
+    let page_table = PageTable::from_token(token);
+    let start = dst as usize;
+    let len = core::mem::size_of::<T>();
+    // 先检查整个区域，避免只写入一部分
+    let mut vpn = VirtAddr::from(start).floor();
+    let end_vpn = VirtAddr::from(start + len).ceil();
+    while vpn < end_vpn {
+        match page_table.translate(vpn) {
+            Some(pte) if pte.is_valid() && pte.writable() && pte.flags().contains(PTEFlags::U) => {}
+            _ => return false,
+        }
+        vpn.step();
+    }
+    let bytes = unsafe { core::slice::from_raw_parts(src as *const T as *const u8, len) };
+    let mut copied = 0;
+    for buffer in translated_byte_buffer(token, dst as *const u8, len) {
+        buffer.copy_from_slice(&bytes[copied..copied + buffer.len()]);
+        copied += buffer.len();
+    }
+    true
+}
+
+/// 从用户地址空间的src处按字节复制出一个T，允许跨越页边界。
+/// 源区域中任一页未映射、或不是用户可读页时返回None
+pub fn copy_from_user<T: Copy>(token: usize, src: *const T) -> Option<T> {
+    let page_table = PageTable::from_token(token);
+    let start = src as usize;
+    let len = core::mem::size_of::<T>();
+    let mut vpn = VirtAddr::from(start).floor();
+    let end_vpn = VirtAddr::from(start + len).ceil();
+    while vpn < end_vpn {
+        match page_table.translate(vpn) {
+            Some(pte) if pte.is_valid() && pte.readable() && pte.flags().contains(PTEFlags::U) => {}
+            _ => return None,
+        }
+        vpn.step();
+    }
+    let mut value = core::mem::MaybeUninit::<T>::uninit();
+    let bytes = unsafe { core::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, len) };
+    let mut copied = 0;
+    for buffer in translated_byte_buffer(token, src as *const u8, len) {
+        bytes[copied..copied + buffer.len()].copy_from_slice(buffer);
+        copied += buffer.len();
+    }
+    Some(unsafe { value.assume_init() })
+}
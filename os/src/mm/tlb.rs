@@ -0,0 +1,87 @@
+//! 多核TLB刷新（TLB shootdown）
+//!
+//! 修改一个可能同时在多个hart上运行的用户地址空间后，本地的`sfence.vma`只能刷新当前hart，
+//! 其他hart的TLB中仍可能残留旧的表项。发起方把刷新请求放进目标hart的邮箱，
+//! 通过SBI发送核间中断，并等待所有目标确认后才返回，之后被解除映射的物理页才能安全复用。
+//!
+//! 目标hart在以下位置处理邮箱：
+//! 1. 核间中断的处理函数（用户态或打开中断的内核态）
+//! 2. 自旋等待锁的循环中，避免发起方持锁等待确认、目标关中断等待同一把锁造成死锁
+//! 3. 空闲循环的wfi返回后
+
+use super::asid::{flush_tlb_all, flush_tlb_asid};
+use crate::config::MAX_HARTS;
+use crate::sbi::send_ipi;
+use crate::sync::{irq_restore, irq_save, SpinLock};
+use crate::task::hart_id;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use riscv::register::sip;
+
+/// 刷新全部TLB表项的请求
+pub const FLUSH_ALL: usize = usize::MAX;
+
+/// 已经启动、会响应核间中断的hart
+static ONLINE_HARTS: AtomicUsize = AtomicUsize::new(0);
+/// 每个hart是否有待处理的刷新请求
+#[allow(clippy::declare_interior_mutable_const)]
+const MAILBOX_INIT: AtomicBool = AtomicBool::new(false);
+static MAILBOX: [AtomicBool; MAX_HARTS] = [MAILBOX_INIT; MAX_HARTS];
+/// 当前请求刷新的ASID，FLUSH_ALL表示全部刷新
+static REQUEST: AtomicUsize = AtomicUsize::new(FLUSH_ALL);
+/// 尚未确认的目标hart个数
+static PENDING_ACKS: AtomicUsize = AtomicUsize::new(0);
+/// 同一时刻只有一个发起方
+static SHOOTDOWN_LOCK: SpinLock<()> = SpinLock::new(());
+
+/// 当前hart开始响应刷新请求，需在使能软件中断之后调用
+pub fn hart_online() {
+    ONLINE_HARTS.fetch_or(1 << hart_id(), Ordering::SeqCst);
+}
+
+fn flush_local(asid: usize) {
+    if asid == FLUSH_ALL {
+        flush_tlb_all();
+    } else {
+        flush_tlb_asid(asid);
+    }
+}
+
+/// 刷新所有hart上带有asid标签的TLB表项，asid为FLUSH_ALL时全部刷新
+pub fn tlb_shootdown(asid: usize) {
+    flush_local(asid);
+    let _guard = SHOOTDOWN_LOCK.lock();
+    let targets = ONLINE_HARTS.load(Ordering::SeqCst) & !(1 << hart_id());
+    if targets == 0 {
+        return;
+    }
+    REQUEST.store(asid, Ordering::SeqCst);
+    PENDING_ACKS.store(targets.count_ones() as usize, Ordering::SeqCst);
+    for (hart, mailbox) in MAILBOX.iter().enumerate() {
+        if targets & (1 << hart) != 0 {
+            mailbox.store(true, Ordering::SeqCst);
+        }
+    }
+    send_ipi(targets);
+    while PENDING_ACKS.load(Ordering::SeqCst) != 0 {
+        core::hint::spin_loop();
+    }
+}
+
+/// 处理当前hart邮箱中的刷新请求并确认，没有请求时什么也不做
+pub fn poll_shootdown() {
+    // 读取hart id和处理请求之间不能被抢占迁移
+    let sie = irq_save();
+    if MAILBOX[hart_id()].swap(false, Ordering::SeqCst) {
+        flush_local(REQUEST.load(Ordering::SeqCst));
+        PENDING_ACKS.fetch_sub(1, Ordering::SeqCst);
+    }
+    irq_restore(sie);
+}
+
+/// 核间中断的处理函数
+pub fn handle_ipi() {
+    unsafe {
+        sip::clear_ssoft();
+    }
+    poll_shootdown();
+}
@@ -0,0 +1,34 @@
+//! 平台级中断控制器（PLIC）
+//!
+//! 目前没有使用中断的设备驱动，所有中断源都保持默认的关闭状态，
+//! 这里只提供领取和完成中断的接口，供外部中断的分发使用。
+
+use crate::config::PLIC_BASE;
+use crate::task::hart_id;
+
+/// 各上下文的领取/完成寄存器相对基址的偏移及间隔
+const CLAIM_OFFSET: usize = 0x20_0004;
+const CONTEXT_STRIDE: usize = 0x1000;
+
+/// 当前hart的S态对应的领取/完成寄存器，qemu virt上每个hart依次有M态和S态两个上下文
+fn claim_register() -> *mut u32 {
+    let context = hart_id() * 2 + 1;
+    (PLIC_BASE + CLAIM_OFFSET + context * CONTEXT_STRIDE) as *mut u32
+}
+
+/// 领取一个待处理的外部中断，没有时返回None
+pub fn claim() -> Option<u32> {
+    let irq = unsafe { claim_register().read_volatile() };
+    if irq == 0 {
+        None
+    } else {
+        Some(irq)
+    }
+}
+
+/// 通知PLIC中断irq处理完毕
+pub fn complete(irq: u32) {
+    unsafe {
+        claim_register().write_volatile(irq);
+    }
+}
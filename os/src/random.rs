@@ -0,0 +1,43 @@
+//! 内核熵源
+//!
+//! 以time寄存器作为种子的xorshift64*伪随机数发生器，
+//! 每次取随机数时再混入当前的time值，避免不同启动之间的序列完全相同。
+
+use crate::sync::SpinLock;
+use crate::timer::get_time;
+use lazy_static::*;
+
+/// xorshift64*发生器的状态
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // 状态不能为0，否则xorshift会一直输出0
+        Self {
+            state: seed | 1,
+        }
+    }
+    fn next(&mut self) -> u64 {
+        self.state ^= get_time() as u64;
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        if self.state == 0 {
+            self.state = 1;
+        }
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+lazy_static! {
+    /// 全局随机数发生器，首次使用时以time寄存器初始化
+    static ref RNG: SpinLock<Rng> = SpinLock::new(Rng::new(get_time() as u64));
+}
+
+/// 获取一个随机数
+#[allow(unused)]
+pub fn rand_usize() -> usize {
+    RNG.lock().next() as usize
+}
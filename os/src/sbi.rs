@@ -6,8 +6,7 @@ pub fn console_putchar(c: usize){
     sbi_rt::legacy::console_putchar(c);
 }
 
-/// 从控制台获取一个字符
-#[allow(unused)]
+/// 从控制台获取一个字符，没有输入时返回usize::MAX
 pub fn console_getchar() -> usize {
     #[allow(deprecated)]
     sbi_rt::legacy::console_getchar()
@@ -18,6 +17,16 @@ pub fn set_timer(timer: usize) {
     sbi_rt::set_timer(timer as _);
 }
 
+/// 通过HSM扩展启动hartid对应的hart，使其从start_addr开始执行，a0为hart id，a1为opaque
+pub fn hart_start(hartid: usize, start_addr: usize, opaque: usize) -> bool {
+    sbi_rt::hart_start(hartid, start_addr, opaque).error == 0
+}
+
+/// 向hart_mask中的各个hart发送核间中断（设置它们的sip.SSIP）
+pub fn send_ipi(hart_mask: usize) {
+    sbi_rt::send_ipi(sbi_rt::HartMask::from_mask_base(hart_mask, 0));
+}
+
 /// sbi关机接口
 pub fn shutdown(failure: bool) -> ! {
     use sbi_rt::{system_reset, NoReason, Shutdown, SystemFailure};
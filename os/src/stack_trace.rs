@@ -1,8 +1,14 @@
 use core::{arch::asm, ptr};
 
-pub unsafe fn print_stack_trace() -> (){ // 当前仅在panic发生时调用函数栈帧打印
-    let mut fp: *const usize;
+pub unsafe fn print_stack_trace() -> (){ // 在panic发生时调用函数栈帧打印
+    let fp: usize;
     asm!("mv {}, fp", out(reg) fp); // 将fp寄存器(汇编代码内)的值赋给fp变量（汇编代码外的高级语言参数）
+    print_stack_trace_from(fp);
+}
+
+/// 从给定的栈帧指针开始打印函数调用栈，内核态Trap用它打印陷入前的调用栈
+pub unsafe fn print_stack_trace_from(fp: usize) {
+    let mut fp = fp as *const usize;
     println!("=== Begin stack trace ===");
     while fp != ptr::null() {
         let saved_ra = *fp.sub(1); // 注意，这里 *const T类型的sub()方法的优先级高于 * 解引用的优先级，因此是先降低地址再解引用
@@ -0,0 +1,104 @@
+//! 银行家算法死锁检测
+//!
+//! 记录进程中每类资源（每个互斥锁或信号量）的可用数量，以及每个线程已分配和正在申请的数量。
+//! 线程申请资源时先假设申请会被满足并做安全性检查：若找不到一个能让所有线程依次完成的序列，
+//! 说明系统将进入不安全状态，申请被拒绝而不是阻塞。
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// 一组同类同步对象的银行家算法状态，资源下标即同步对象id，线程下标即线程id
+pub struct Banker {
+    /// 每类资源当前可用的数量
+    available: Vec<usize>,
+    /// allocation[tid][res]：线程已经持有的资源数量
+    allocation: Vec<Vec<usize>>,
+    /// need[tid][res]：线程正在申请、尚未得到的资源数量
+    need: Vec<Vec<usize>>,
+}
+
+impl Banker {
+    /// 创建一个没有任何资源的状态
+    pub fn new() -> Self {
+        Self {
+            available: Vec::new(),
+            allocation: Vec::new(),
+            need: Vec::new(),
+        }
+    }
+    /// 登记资源res，初始可用数量为count
+    pub fn add_resource(&mut self, res: usize, count: usize) {
+        if self.available.len() <= res {
+            self.available.resize(res + 1, 0);
+        }
+        self.available[res] = count;
+        for row in self.allocation.iter_mut().chain(self.need.iter_mut()) {
+            row.resize(self.available.len(), 0);
+            row[res] = 0;
+        }
+    }
+    /// 保证线程tid有对应的行
+    fn ensure_thread(&mut self, tid: usize) {
+        while self.allocation.len() <= tid {
+            self.allocation.push(vec![0; self.available.len()]);
+            self.need.push(vec![0; self.available.len()]);
+        }
+        // 线程登记之后新增的资源需要补齐列
+        self.allocation[tid].resize(self.available.len(), 0);
+        self.need[tid].resize(self.available.len(), 0);
+    }
+    /// 线程tid申请一个资源res，返回申请后系统是否仍处于安全状态；不安全时撤销这次申请
+    pub fn request(&mut self, tid: usize, res: usize) -> bool {
+        self.ensure_thread(tid);
+        self.need[tid][res] += 1;
+        if self.is_safe() {
+            true
+        } else {
+            self.need[tid][res] -= 1;
+            false
+        }
+    }
+    /// 线程tid得到了申请的资源res
+    pub fn acquire(&mut self, tid: usize, res: usize) {
+        self.ensure_thread(tid);
+        self.available[res] = self.available[res].saturating_sub(1);
+        self.allocation[tid][res] += 1;
+        self.need[tid][res] = self.need[tid][res].saturating_sub(1);
+    }
+    /// 线程tid释放一个资源res；信号量可以由未持有它的线程释放
+    pub fn release(&mut self, tid: usize, res: usize) {
+        self.ensure_thread(tid);
+        self.available[res] += 1;
+        self.allocation[tid][res] = self.allocation[tid][res].saturating_sub(1);
+    }
+    /// 线程退出时清空其所在的行
+    pub fn remove_thread(&mut self, tid: usize) {
+        if tid < self.allocation.len() {
+            self.allocation[tid].iter_mut().for_each(|count| *count = 0);
+            self.need[tid].iter_mut().for_each(|count| *count = 0);
+        }
+    }
+    /// 安全性检查：能否找到一个顺序，使每个线程的申请都能被满足并最终释放其持有的资源
+    fn is_safe(&self) -> bool {
+        let mut work = self.available.clone();
+        let mut finish = vec![false; self.allocation.len()];
+        loop {
+            let next = (0..finish.len()).find(|&tid| {
+                !finish[tid]
+                    && self.need[tid]
+                        .iter()
+                        .zip(work.iter())
+                        .all(|(need, work)| need <= work)
+            });
+            match next {
+                Some(tid) => {
+                    finish[tid] = true;
+                    for (work, alloc) in work.iter_mut().zip(self.allocation[tid].iter()) {
+                        *work += alloc;
+                    }
+                }
+                None => return finish.iter().all(|&finished| finished),
+            }
+        }
+    }
+}
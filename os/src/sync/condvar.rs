@@ -0,0 +1,37 @@
+//! 条件变量
+
+use super::{Mutex, SpinLock, WaitQueue};
+use crate::task::{block_current_and_run_next, current_task_handle};
+use alloc::sync::Arc;
+
+/// 条件变量
+pub struct Condvar {
+    wait_queue: SpinLock<WaitQueue>,
+}
+
+impl Condvar {
+    /// 创建一个没有等待者的条件变量
+    pub fn new() -> Self {
+        Self {
+            wait_queue: SpinLock::new(WaitQueue::new()),
+        }
+    }
+    /// 唤醒一个等待者
+    pub fn signal(&self) {
+        self.wait_queue.lock().wake_one();
+    }
+    /// 释放mutex并阻塞，被唤醒后重新获取mutex；当前任务没有持有mutex时返回false
+    pub fn wait(&self, mutex: Arc<dyn Mutex>) -> bool {
+        // 先登记再解锁，不会错过解锁与阻塞之间的signal
+        let mut wait_queue = self.wait_queue.lock();
+        wait_queue.add_current();
+        if !mutex.unlock() {
+            wait_queue.remove(current_task_handle());
+            return false;
+        }
+        drop(wait_queue);
+        block_current_and_run_next();
+        mutex.lock();
+        true
+    }
+}
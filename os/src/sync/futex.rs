@@ -0,0 +1,49 @@
+//! futex：用户态锁的内核等待队列
+//!
+//! 用户态锁在无竞争时只做原子操作，有竞争时才通过futex系统调用进入内核等待。
+//! 等待队列以用户地址翻译得到的物理地址为键，因此映射到同一物理页的不同虚拟地址共享同一个队列。
+
+use super::{SpinLock, WaitQueue};
+use crate::task::block_current_and_run_next;
+use alloc::collections::BTreeMap;
+use lazy_static::*;
+
+lazy_static! {
+    /// 物理地址 -> 在该地址上等待的任务
+    static ref FUTEX_QUEUES: SpinLock<BTreeMap<usize, WaitQueue>> =
+        SpinLock::new(BTreeMap::new());
+}
+
+/// 若pa处的u32值仍为val，则把当前任务阻塞在pa对应的队列上，返回是否阻塞过
+pub fn futex_wait(pa: usize, val: u32) -> bool {
+    let mut queues = FUTEX_QUEUES.lock();
+    // 检查与登记之间不会被其他任务打断，不会错过唤醒
+    if unsafe { (pa as *const u32).read_volatile() } != val {
+        return false;
+    }
+    let queue = queues.entry(pa).or_insert_with(WaitQueue::new);
+    queue.remove_exited();
+    queue.add_current();
+    drop(queues);
+    block_current_and_run_next();
+    true
+}
+
+/// 唤醒最多count个在pa上等待的任务，返回实际唤醒的个数
+pub fn futex_wake(pa: usize, count: usize) -> usize {
+    let mut queues = FUTEX_QUEUES.lock();
+    let Some(queue) = queues.get_mut(&pa) else {
+        return 0;
+    };
+    // wake_one跳过已经退出的等待者，它们不计入唤醒的个数
+    let mut woken = 0;
+    while woken < count && queue.wake_one().is_some() {
+        woken += 1;
+    }
+    // 没有被唤醒到的已退出等待者也一并清理，否则它们会一直占着队列
+    queue.remove_exited();
+    if queue.is_empty() {
+        queues.remove(&pa);
+    }
+    woken
+}
@@ -0,0 +1,144 @@
+//! 关中断原语
+//!
+//! 每个hart记录关中断的嵌套深度和最外层关中断前的sstatus.SIE，
+//! 只有最外层恢复时才重新打开中断，因此守卫不按获取的逆序释放也不会提前开中断。
+//! 同时在调试构建中记录每个hart持有的带等级的锁，检查获取顺序。
+//! 每个hart还记录持有的自旋锁个数，持有自旋锁时不能在内核中被抢占。
+
+use crate::config::MAX_HARTS;
+use crate::task::hart_id;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering::Relaxed};
+use riscv::register::sstatus;
+
+/// 每个hart的关中断状态，只会被所属的hart在关中断时访问
+struct HartIrqState {
+    /// 关中断的嵌套深度
+    depth: AtomicUsize,
+    /// 最外层关中断之前SIE是否打开
+    saved_sie: AtomicBool,
+    /// 持有的带等级的锁，第i位表示持有等级为i的锁
+    held_ranks: AtomicUsize,
+    /// 持有的自旋锁个数
+    lock_depth: AtomicUsize,
+}
+
+impl HartIrqState {
+    const fn new() -> Self {
+        Self {
+            depth: AtomicUsize::new(0),
+            saved_sie: AtomicBool::new(false),
+            held_ranks: AtomicUsize::new(0),
+            lock_depth: AtomicUsize::new(0),
+        }
+    }
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const HART_IRQ_STATE_INIT: HartIrqState = HartIrqState::new();
+static HART_IRQ_STATE: [HartIrqState; MAX_HARTS] = [HART_IRQ_STATE_INIT; MAX_HARTS];
+
+/// 关闭当前hart的中断，与[`pop_off`]配对使用
+pub fn push_off() {
+    let sie = sstatus::read().sie();
+    unsafe {
+        sstatus::clear_sie();
+    }
+    let state = &HART_IRQ_STATE[hart_id()];
+    if state.depth.fetch_add(1, Relaxed) == 0 {
+        state.saved_sie.store(sie, Relaxed);
+    }
+}
+
+/// 撤销一次[`push_off`]，最外层撤销时恢复关中断之前的SIE
+pub fn pop_off() {
+    assert!(!sstatus::read().sie(), "pop_off with interrupts enabled");
+    let state = &HART_IRQ_STATE[hart_id()];
+    let depth = state.depth.fetch_sub(1, Relaxed);
+    assert!(depth > 0, "pop_off without push_off");
+    if depth == 1 && state.saved_sie.load(Relaxed) {
+        unsafe {
+            sstatus::set_sie();
+        }
+    }
+}
+
+/// 关闭当前hart的中断，返回之前SIE是否打开；
+/// 与push_off不同，状态由调用者保存，可以跨越任务切换
+pub fn irq_save() -> bool {
+    let sie = sstatus::read().sie();
+    unsafe {
+        sstatus::clear_sie();
+    }
+    sie
+}
+
+/// 恢复[`irq_save`]之前的中断状态
+pub fn irq_restore(sie: bool) {
+    if sie {
+        unsafe {
+            sstatus::set_sie();
+        }
+    }
+}
+
+/// 关中断守卫，存在期间当前hart不响应中断
+pub struct IrqGuard;
+
+impl IrqGuard {
+    /// 关闭中断，守卫drop时恢复
+    pub fn new() -> Self {
+        push_off();
+        Self
+    }
+}
+
+impl Drop for IrqGuard {
+    fn drop(&mut self) {
+        pop_off();
+    }
+}
+
+/// 获取自旋锁之前调用，关中断修改计数，避免读取hart id后被抢占迁移到其他hart
+pub fn preempt_disable() {
+    push_off();
+    HART_IRQ_STATE[hart_id()].lock_depth.fetch_add(1, Relaxed);
+    pop_off();
+}
+
+/// 释放自旋锁之后调用
+pub fn preempt_enable() {
+    push_off();
+    HART_IRQ_STATE[hart_id()].lock_depth.fetch_sub(1, Relaxed);
+    pop_off();
+}
+
+/// 当前hart没有持有自旋锁，被中断的内核代码可以被抢占
+pub fn preemptible() -> bool {
+    HART_IRQ_STATE[hart_id()].lock_depth.load(Relaxed) == 0
+}
+
+/// 获取等级为rank的锁之前调用，当前hart已经持有等级不低于rank的锁时说明获取顺序错误
+pub fn acquire_rank(rank: usize) {
+    if !cfg!(debug_assertions) || rank == 0 {
+        return;
+    }
+    let state = &HART_IRQ_STATE[hart_id()];
+    let held = state.held_ranks.load(Relaxed);
+    assert!(
+        held >> rank == 0,
+        "lock order violation: acquiring rank {} while holding ranks {:#b}",
+        rank,
+        held
+    );
+    state.held_ranks.store(held | 1 << rank, Relaxed);
+}
+
+/// 释放等级为rank的锁之后调用
+pub fn release_rank(rank: usize) {
+    if !cfg!(debug_assertions) || rank == 0 {
+        return;
+    }
+    HART_IRQ_STATE[hart_id()]
+        .held_ranks
+        .fetch_and(!(1 << rank), Relaxed);
+}
@@ -1,2 +1,20 @@
-mod up;
-pub use self::up::UPSafeCell;
\ No newline at end of file
+mod banker;
+mod condvar;
+mod futex;
+mod irq;
+mod mutex;
+mod semaphore;
+mod spin;
+mod wait_queue;
+
+pub use self::banker::Banker;
+pub use self::condvar::Condvar;
+pub use self::futex::{futex_wait, futex_wake};
+pub use self::irq::{irq_restore, irq_save, preemptible, IrqGuard};
+pub use self::mutex::{Mutex, MutexBlocking, MutexSpin};
+pub use self::semaphore::Semaphore;
+pub use self::spin::{
+    SpinLock, SpinLockGuard, SpinNoIrqLock, RANK_FRAME_ALLOCATOR, RANK_KERNEL_SPACE,
+    RANK_TASK_MANAGER,
+};
+pub use self::wait_queue::{wait_on, WaitQueue};
@@ -0,0 +1,114 @@
+//! 互斥锁
+//!
+//! 自旋版本在锁被占用时让出CPU后重试，阻塞版本把等待者挂在等待队列上，
+//! 解锁时直接把锁交给队首的等待者。
+//! 两者都记录持有锁的任务，只有持有者才能解锁。
+
+use super::{SpinLock, WaitQueue};
+use crate::task::{
+    block_current_and_run_next, current_task_handle, suspend_current_and_run_next, TaskHandle,
+};
+
+/// 互斥锁接口，互斥锁由进程内的所有线程共享
+pub trait Mutex: Send + Sync {
+    /// 加锁，锁被占用时等待
+    fn lock(&self);
+    /// 解锁，当前任务没有持有锁时返回false
+    fn unlock(&self) -> bool;
+    /// 锁是否由task持有
+    fn is_held_by(&self, task: TaskHandle) -> bool;
+}
+
+/// 让权等待的自旋锁
+pub struct MutexSpin {
+    /// 持有锁的任务，None表示未加锁
+    owner: SpinLock<Option<TaskHandle>>,
+}
+
+impl MutexSpin {
+    /// 创建一个未加锁的自旋锁
+    pub fn new() -> Self {
+        Self {
+            owner: SpinLock::new(None),
+        }
+    }
+}
+
+impl Mutex for MutexSpin {
+    fn lock(&self) {
+        let current = current_task_handle();
+        loop {
+            let mut owner = self.owner.lock();
+            if owner.is_some() {
+                drop(owner);
+                suspend_current_and_run_next();
+                continue;
+            }
+            *owner = Some(current);
+            return;
+        }
+    }
+    fn unlock(&self) -> bool {
+        let current = current_task_handle();
+        let mut owner = self.owner.lock();
+        if *owner != Some(current) {
+            return false;
+        }
+        *owner = None;
+        true
+    }
+    fn is_held_by(&self, task: TaskHandle) -> bool {
+        *self.owner.lock() == Some(task)
+    }
+}
+
+/// 阻塞式互斥锁
+pub struct MutexBlocking {
+    inner: SpinLock<MutexBlockingInner>,
+}
+
+struct MutexBlockingInner {
+    /// 持有锁的任务，None表示未加锁
+    owner: Option<TaskHandle>,
+    wait_queue: WaitQueue,
+}
+
+impl MutexBlocking {
+    /// 创建一个未加锁的阻塞式互斥锁
+    pub fn new() -> Self {
+        Self {
+            inner: SpinLock::new(MutexBlockingInner {
+                owner: None,
+                wait_queue: WaitQueue::new(),
+            }),
+        }
+    }
+}
+
+impl Mutex for MutexBlocking {
+    fn lock(&self) {
+        let current = current_task_handle();
+        let mut inner = self.inner.lock();
+        if inner.owner.is_some() {
+            inner.wait_queue.add_current();
+            drop(inner);
+            // 被唤醒时锁已经交给了当前任务
+            block_current_and_run_next();
+        } else {
+            inner.owner = Some(current);
+        }
+    }
+    fn unlock(&self) -> bool {
+        let current = current_task_handle();
+        let mut inner = self.inner.lock();
+        if inner.owner != Some(current) {
+            return false;
+        }
+        // 有等待者时锁保持占用状态，直接交给被唤醒的任务；已经退出的等待者被跳过
+        inner.owner = inner.wait_queue.wake_one();
+        true
+    }
+    fn is_held_by(&self, task: TaskHandle) -> bool {
+        self.inner.lock().owner == Some(task)
+    }
+}
@@ -0,0 +1,44 @@
+//! 计数信号量
+
+use super::{SpinLock, WaitQueue};
+use crate::task::block_current_and_run_next;
+
+/// 计数信号量，count为负时其绝对值为等待者的个数
+pub struct Semaphore {
+    inner: SpinLock<SemaphoreInner>,
+}
+
+struct SemaphoreInner {
+    count: isize,
+    wait_queue: WaitQueue,
+}
+
+impl Semaphore {
+    /// 创建一个初始资源数为res_count的信号量
+    pub fn new(res_count: usize) -> Self {
+        Self {
+            inner: SpinLock::new(SemaphoreInner {
+                count: res_count as isize,
+                wait_queue: WaitQueue::new(),
+            }),
+        }
+    }
+    /// V操作：释放一个资源，有等待者时唤醒一个
+    pub fn up(&self) {
+        let mut inner = self.inner.lock();
+        inner.count += 1;
+        if inner.count <= 0 {
+            inner.wait_queue.wake_one();
+        }
+    }
+    /// P操作：申请一个资源，没有资源时阻塞
+    pub fn down(&self) {
+        let mut inner = self.inner.lock();
+        inner.count -= 1;
+        if inner.count < 0 {
+            inner.wait_queue.add_current();
+            drop(inner);
+            block_current_and_run_next();
+        }
+    }
+}
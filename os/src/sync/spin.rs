@@ -0,0 +1,138 @@
+//! 实现SpinLock<T>和SpinNoIrqLock<T>结构体，用于多处理器间的互斥访问
+//!
+//! 会在中断处理中获取的锁必须使用SpinNoIrqLock，否则持锁时被中断、
+//! 中断处理再次获取同一把锁会造成死锁。持有任何自旋锁时内核都不会被抢占。
+
+use super::irq::{
+    acquire_rank, pop_off, preempt_disable, preempt_enable, push_off, release_rank,
+};
+use crate::mm::poll_shootdown;
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// 自旋锁，获取失败时忙等直到其他核释放
+pub struct SpinLock<T> {
+    locked: AtomicBool,
+    inner: UnsafeCell<T>,
+}
+
+// 内部数据只能在持有锁时通过守卫访问，因此可以在多个核之间共享；
+// 数据会被不同的核访问，要求T可以在核之间转移
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+
+impl<T> SpinLock<T> {
+    /// 构造一个未上锁的自旋锁
+    pub const fn new(value: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            inner: UnsafeCell::new(value),
+        }
+    }
+    /// 获取锁，返回的守卫在drop时自动释放锁
+    pub fn lock(&self) -> SpinLockGuard<'_, T> {
+        preempt_disable();
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            // 先用普通读等待锁被释放，避免反复写同一缓存行；
+            // 等待期间处理TLB刷新请求，持锁的一方可能正在等待本hart确认
+            while self.locked.load(Ordering::Relaxed) {
+                poll_shootdown();
+                core::hint::spin_loop();
+            }
+        }
+        SpinLockGuard { lock: self }
+    }
+}
+
+/// 自旋锁守卫
+pub struct SpinLockGuard<'a, T> {
+    lock: &'a SpinLock<T>,
+}
+
+impl<T> Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.inner.get() }
+    }
+}
+
+impl<T> DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.inner.get() }
+    }
+}
+
+impl<T> Drop for SpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.locked.store(false, Ordering::Release);
+        preempt_enable();
+    }
+}
+
+/// 锁等级：同一hart上只能按等级递增的顺序获取，0表示不参与检查
+pub const RANK_TASK_MANAGER: usize = 1;
+pub const RANK_KERNEL_SPACE: usize = 2;
+pub const RANK_FRAME_ALLOCATOR: usize = 3;
+
+/// 关中断的自旋锁，持锁期间当前hart不响应中断，释放后恢复获取前的sstatus.SIE
+/// 与内部的SpinLock一样，只有T: Send时才能在核之间共享
+pub struct SpinNoIrqLock<T> {
+    inner: SpinLock<T>,
+    rank: usize,
+}
+
+#[allow(unused)]
+impl<T> SpinNoIrqLock<T> {
+    /// 构造一个不参与顺序检查的锁
+    pub const fn new(value: T) -> Self {
+        Self::new_ranked(value, 0)
+    }
+    /// 构造一个等级为rank的锁，调试构建中检查获取顺序
+    pub const fn new_ranked(value: T, rank: usize) -> Self {
+        Self {
+            inner: SpinLock::new(value),
+            rank,
+        }
+    }
+    /// 关中断并获取锁
+    pub fn lock(&self) -> SpinNoIrqLockGuard<'_, T> {
+        push_off();
+        acquire_rank(self.rank);
+        SpinNoIrqLockGuard {
+            guard: Some(self.inner.lock()),
+            rank: self.rank,
+        }
+    }
+}
+
+/// 关中断自旋锁守卫
+pub struct SpinNoIrqLockGuard<'a, T> {
+    guard: Option<SpinLockGuard<'a, T>>,
+    rank: usize,
+}
+
+impl<T> Deref for SpinNoIrqLockGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.guard.as_ref().unwrap()
+    }
+}
+
+impl<T> DerefMut for SpinNoIrqLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.guard.as_mut().unwrap()
+    }
+}
+
+impl<T> Drop for SpinNoIrqLockGuard<'_, T> {
+    fn drop(&mut self) {
+        // 先释放锁再恢复中断
+        self.guard.take();
+        release_rank(self.rank);
+        pop_off();
+    }
+}
@@ -0,0 +1,68 @@
+//! 等待队列
+//!
+//! 内核各子系统在条件不满足时把当前任务加入等待队列并阻塞，
+//! 条件满足时再从队列中取出任务唤醒。
+
+use super::SpinLock;
+use crate::task::{
+    block_current_and_run_next, current_task_handle, task_alive, wakeup_task, TaskHandle,
+};
+use alloc::collections::VecDeque;
+
+/// 按到达顺序排列的阻塞任务
+pub struct WaitQueue {
+    queue: VecDeque<TaskHandle>,
+}
+
+#[allow(unused)]
+impl WaitQueue {
+    /// 创建一个空的等待队列
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+    /// 把当前任务加入队列末尾，只加入而不阻塞，
+    /// 调用者需要在释放自己持有的借用后再调用[`block_current_and_run_next`]
+    pub fn add_current(&mut self) {
+        self.queue.push_back(current_task_handle());
+    }
+    /// 唤醒队首的任务，返回被唤醒任务的句柄；已经退出的任务直接出队，不算作唤醒
+    pub fn wake_one(&mut self) -> Option<TaskHandle> {
+        while let Some(task) = self.queue.pop_front() {
+            if wakeup_task(task) {
+                return Some(task);
+            }
+        }
+        None
+    }
+    /// 唤醒队列中的所有任务，返回被唤醒的任务个数
+    pub fn wake_all(&mut self) -> usize {
+        let mut count = 0;
+        while self.wake_one().is_some() {
+            count += 1;
+        }
+        count
+    }
+    /// 从队列中移除一个任务（例如等待超时），不唤醒它
+    pub fn remove(&mut self, task: TaskHandle) -> bool {
+        let len = self.queue.len();
+        self.queue.retain(|&waiter| waiter != task);
+        self.queue.len() != len
+    }
+    /// 移除已经退出的任务，它们不会再被唤醒
+    pub fn remove_exited(&mut self) {
+        self.queue.retain(|&waiter| task_alive(waiter));
+    }
+    /// 队列是否为空
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+/// 把当前任务阻塞在queue上，直到被唤醒
+#[allow(unused)]
+pub fn wait_on(queue: &SpinLock<WaitQueue>) {
+    queue.lock().add_current();
+    block_current_and_run_next();
+}
@@ -0,0 +1,14 @@
+//! 系统调用返回的错误码，与Linux保持一致，返回时取负值
+
+/// 操作不被允许，例如解锁不属于自己的互斥锁
+pub const EPERM: isize = 1;
+/// 指定的进程或线程不存在
+pub const ESRCH: isize = 3;
+/// 资源暂时不可用，需要重试
+pub const EAGAIN: isize = 11;
+/// 参数中的用户地址无效
+pub const EFAULT: isize = 14;
+/// 参数无效
+pub const EINVAL: isize = 22;
+/// 继续申请资源将导致死锁
+pub const EDEADLK: isize = 35;
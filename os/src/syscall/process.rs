@@ -1,13 +1,23 @@
 //! app管理的系统调用
 
 
-use crate::task::{change_program_brk, exit_current_and_run_next, suspend_current_and_run_next};
-use crate::timer::get_time_ms;
+use super::errno::{EFAULT, EINVAL};
+use crate::config::CLOCK_FREQ;
+use crate::mm::copy_to_user;
+use crate::task::{
+    block_current_and_run_next, change_current_nice, change_program_brk, current_process,
+    current_task_handle, current_usage, current_user_token, exit_current_and_run_next,
+    get_current_deadline, set_current_deadline, set_current_priority,
+    suspend_current_and_run_next, DeadlineParams,
+};
+use crate::timer::{
+    add_sleeper, get_time, monotonic_time, realtime, TimeSpec, TimeVal, TICKS_PER_SEC,
+};
 
 
 pub fn sys_exit(exit_id: i32) -> ! {
     println!("[kernel] App exited with code {}", exit_id);
-    exit_current_and_run_next();
+    exit_current_and_run_next(exit_id);
     panic!("Unreachable in sys_exit!");
 }
 
@@ -16,8 +26,49 @@ pub fn sys_yield() -> isize {
     0
 }
 
-pub fn sys_get_time() -> isize {
-    get_time_ms() as isize
+/// 睡眠ms毫秒，期间任务处于阻塞态，不会被调度
+pub fn sys_sleep(ms: usize) -> isize {
+    if ms == 0 {
+        suspend_current_and_run_next();
+        return 0;
+    }
+    add_sleeper(get_time() + ms * (CLOCK_FREQ / 1000), current_task_handle());
+    block_current_and_run_next();
+    0
+}
+
+/// 获取开机以来经过的时间（微秒精度），写入ts指向的TimeVal，时区参数被忽略
+pub fn sys_get_time(ts: *mut TimeVal, _tz: usize) -> isize {
+    let now = monotonic_time();
+    let value = TimeVal {
+        sec: now.sec,
+        usec: now.nsec / 1000,
+    };
+    if !copy_to_user(current_user_token(), ts, &value) {
+        return -EFAULT;
+    }
+    0
+}
+
+const CLOCK_REALTIME: usize = 0;
+const CLOCK_MONOTONIC: usize = 1;
+
+/// 读取指定的时钟，写入tp指向的TimeSpec
+pub fn sys_clock_gettime(clock_id: usize, tp: *mut TimeSpec) -> isize {
+    let value = match clock_id {
+        CLOCK_REALTIME => realtime(),
+        CLOCK_MONOTONIC => monotonic_time(),
+        _ => return -EINVAL,
+    };
+    if !copy_to_user(current_user_token(), tp, &value) {
+        return -EFAULT;
+    }
+    0
+}
+
+/// 获取当前进程的id
+pub fn sys_getpid() -> isize {
+    current_process().pid as isize
 }
 
 /// 改变数据段大小
@@ -28,3 +79,118 @@ pub fn sys_sbrk(size: i32) -> isize {
         -1
     }
 }
+
+/// 设置当前任务的优先级，优先级至少为2，成功时返回设置的优先级
+pub fn sys_set_priority(prio: isize) -> isize {
+    if prio < 2 {
+        return -1;
+    }
+    set_current_priority(prio as usize);
+    prio
+}
+
+/// 将当前任务的nice值增加increment（结果限制在[-20, 19]），返回新的nice值
+pub fn sys_nice(increment: isize) -> isize {
+    change_current_nice(increment)
+}
+
+/// 实时任务的调度参数，时间单位为毫秒
+#[repr(C)]
+pub struct SchedAttr {
+    pub runtime: usize,
+    pub deadline: usize,
+    pub period: usize,
+    /// 错过截止期限的次数
+    pub misses: usize,
+}
+
+/// 将当前任务设为EDF实时任务，参数不合法或未通过准入控制（总利用率超过1）时返回-EINVAL
+pub fn sys_sched_setattr(runtime: usize, deadline: usize, period: usize) -> isize {
+    let params = DeadlineParams {
+        runtime,
+        deadline,
+        period,
+    };
+    if set_current_deadline(params) {
+        0
+    } else {
+        -EINVAL
+    }
+}
+
+/// 获取当前任务的实时参数，当前任务不是实时任务时返回-EINVAL，attr无效时返回-EFAULT
+pub fn sys_sched_getattr(attr: *mut SchedAttr) -> isize {
+    let Some((params, misses)) = get_current_deadline() else {
+        return -EINVAL;
+    };
+    let value = SchedAttr {
+        runtime: params.runtime,
+        deadline: params.deadline,
+        period: params.period,
+        misses,
+    };
+    if !copy_to_user(current_user_token(), attr, &value) {
+        return -EFAULT;
+    }
+    0
+}
+
+/// times系统调用返回的进程时间，单位为时钟滴答（每秒TICKS_PER_SEC个）
+#[repr(C)]
+pub struct Tms {
+    pub tms_utime: usize,
+    pub tms_stime: usize,
+    pub tms_cutime: usize,
+    pub tms_cstime: usize,
+}
+
+/// getrusage系统调用返回的资源使用情况
+#[repr(C)]
+pub struct Rusage {
+    pub ru_utime: TimeVal,
+    pub ru_stime: TimeVal,
+    /// 主动让出CPU的次数
+    pub ru_nvcsw: usize,
+    /// 被抢占的次数
+    pub ru_nivcsw: usize,
+}
+
+/// 只支持统计调用者自身
+const RUSAGE_SELF: isize = 0;
+
+fn cycles_to_ticks(cycles: usize) -> usize {
+    cycles / (CLOCK_FREQ / TICKS_PER_SEC)
+}
+
+/// 获取当前任务的用户态和内核态时间，返回开机以来的时钟滴答数
+pub fn sys_times(tms: *mut Tms) -> isize {
+    let (user_time, kernel_time, _, _) = current_usage();
+    let value = Tms {
+        tms_utime: cycles_to_ticks(user_time),
+        tms_stime: cycles_to_ticks(kernel_time),
+        tms_cutime: 0,
+        tms_cstime: 0,
+    };
+    if !copy_to_user(current_user_token(), tms, &value) {
+        return -EFAULT;
+    }
+    cycles_to_ticks(get_time()) as isize
+}
+
+/// 获取当前任务的资源使用情况，who只支持RUSAGE_SELF
+pub fn sys_getrusage(who: isize, usage: *mut Rusage) -> isize {
+    if who != RUSAGE_SELF {
+        return -EINVAL;
+    }
+    let (user_time, kernel_time, nvcsw, nivcsw) = current_usage();
+    let value = Rusage {
+        ru_utime: TimeVal::from_cycles(user_time),
+        ru_stime: TimeVal::from_cycles(kernel_time),
+        ru_nvcsw: nvcsw,
+        ru_nivcsw: nivcsw,
+    };
+    if !copy_to_user(current_user_token(), usage, &value) {
+        return -EFAULT;
+    }
+    0
+}
@@ -0,0 +1,78 @@
+//! 信号相关的系统调用
+
+use super::errno::{EFAULT, EINVAL, ESRCH};
+use crate::mm::{copy_from_user, copy_to_user};
+use crate::task::{
+    current_process, current_signal_mask, current_user_token, send_signal,
+    set_current_signal_mask, signal_return, SignalAction, SignalFlags, SIG_BLOCK, SIG_SETMASK,
+    SIG_UNBLOCK,
+};
+
+/// 向进程pid发送编号为signum的信号
+pub fn sys_kill(pid: usize, signum: usize) -> isize {
+    let Some(signal) = SignalFlags::from_signum(signum) else {
+        return -EINVAL;
+    };
+    if send_signal(pid, signal) {
+        0
+    } else {
+        -ESRCH
+    }
+}
+
+/// 设置信号signum的处理动作，action或old_action为空指针时不设置或不返回
+pub fn sys_sigaction(
+    signum: usize,
+    action: *const SignalAction,
+    old_action: *mut SignalAction,
+) -> isize {
+    let Some(signal) = SignalFlags::from_signum(signum) else {
+        return -EINVAL;
+    };
+    if SignalFlags::uncatchable().contains(signal) {
+        return -EINVAL;
+    }
+    let token = current_user_token();
+    let new_action = if action.is_null() {
+        None
+    } else {
+        match copy_from_user(token, action) {
+            Some(action) => Some(action),
+            None => return -EFAULT,
+        }
+    };
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    if !old_action.is_null()
+        && !copy_to_user(token, old_action, &process_inner.signal_actions.table[signum])
+    {
+        return -EFAULT;
+    }
+    if let Some(action) = new_action {
+        process_inner.signal_actions.table[signum] = action;
+    }
+    0
+}
+
+/// 按how修改当前线程的屏蔽信号集，返回修改前的屏蔽信号集
+pub fn sys_sigprocmask(how: usize, set: u32) -> isize {
+    let old = current_signal_mask();
+    let set = SignalFlags::from_bits_retain(set);
+    let new = match how {
+        SIG_BLOCK => old | set,
+        SIG_UNBLOCK => old - set,
+        SIG_SETMASK => set,
+        _ => return -EINVAL,
+    };
+    set_current_signal_mask(new);
+    old.bits() as isize
+}
+
+/// 从信号处理函数返回被打断的位置
+pub fn sys_sigreturn() -> isize {
+    match signal_return() {
+        // 系统调用的返回值会写入a0，这里返回恢复后的a0
+        Some(a0) => a0 as isize,
+        None => -EINVAL,
+    }
+}
@@ -0,0 +1,181 @@
+//! 同步原语相关的系统调用，同步对象以进程内的id引用
+
+use super::errno::{EAGAIN, EDEADLK, EFAULT, EINVAL, EPERM};
+use crate::mm::translated_user_pa;
+use crate::sync::{futex_wait, futex_wake, Condvar, Mutex, MutexBlocking, MutexSpin, Semaphore};
+use crate::task::{
+    current_process, current_task_handle, current_tid, current_user_token,
+    ProcessControlBlockInner,
+};
+use alloc::sync::Arc;
+
+/// 创建互斥锁，blocking为0时创建自旋锁，否则创建阻塞式互斥锁，返回互斥锁id
+pub fn sys_mutex_create(blocking: bool) -> isize {
+    let mutex: Arc<dyn Mutex> = if blocking {
+        Arc::new(MutexBlocking::new())
+    } else {
+        Arc::new(MutexSpin::new())
+    };
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let id = ProcessControlBlockInner::insert_object(&mut process_inner.mutex_list, mutex);
+    process_inner.mutex_banker.add_resource(id, 1);
+    id as isize
+}
+
+/// 获取互斥锁，开启死锁检测时若加锁会使系统进入不安全状态则返回-EDEADLK
+pub fn sys_mutex_lock(mutex_id: usize) -> isize {
+    let tid = current_tid();
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let Some(Some(mutex)) = process_inner.mutex_list.get(mutex_id).cloned() else {
+        return -EINVAL;
+    };
+    if process_inner.deadlock_detect && !process_inner.mutex_banker.request(tid, mutex_id) {
+        return -EDEADLK;
+    }
+    // 加锁可能阻塞，之前必须释放进程控制块的借用
+    drop(process_inner);
+    mutex.lock();
+    process.inner_exclusive_access().mutex_banker.acquire(tid, mutex_id);
+    0
+}
+
+/// 释放互斥锁，当前线程没有持有该锁时返回-EPERM
+pub fn sys_mutex_unlock(mutex_id: usize) -> isize {
+    let tid = current_tid();
+    let current = current_task_handle();
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let Some(Some(mutex)) = process_inner.mutex_list.get(mutex_id).cloned() else {
+        return -EINVAL;
+    };
+    // 先确认持有者再修改死锁检测的状态，解锁失败时不会凭空多出可用资源
+    if !mutex.is_held_by(current) {
+        return -EPERM;
+    }
+    process_inner.mutex_banker.release(tid, mutex_id);
+    drop(process_inner);
+    mutex.unlock();
+    0
+}
+
+/// 创建初始资源数为res_count的信号量，返回信号量id
+pub fn sys_semaphore_create(res_count: usize) -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let semaphore = Arc::new(Semaphore::new(res_count));
+    let id = ProcessControlBlockInner::insert_object(&mut process_inner.semaphore_list, semaphore);
+    process_inner.semaphore_banker.add_resource(id, res_count);
+    id as isize
+}
+
+/// 信号量的V操作
+pub fn sys_semaphore_up(sem_id: usize) -> isize {
+    let tid = current_tid();
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let Some(Some(semaphore)) = process_inner.semaphore_list.get(sem_id).cloned() else {
+        return -EINVAL;
+    };
+    process_inner.semaphore_banker.release(tid, sem_id);
+    drop(process_inner);
+    semaphore.up();
+    0
+}
+
+/// 信号量的P操作，开启死锁检测时若申请会使系统进入不安全状态则返回-EDEADLK
+pub fn sys_semaphore_down(sem_id: usize) -> isize {
+    let tid = current_tid();
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let Some(Some(semaphore)) = process_inner.semaphore_list.get(sem_id).cloned() else {
+        return -EINVAL;
+    };
+    if process_inner.deadlock_detect && !process_inner.semaphore_banker.request(tid, sem_id) {
+        return -EDEADLK;
+    }
+    drop(process_inner);
+    semaphore.down();
+    process.inner_exclusive_access().semaphore_banker.acquire(tid, sem_id);
+    0
+}
+
+/// 创建条件变量，返回条件变量id
+pub fn sys_condvar_create() -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    let condvar = Arc::new(Condvar::new());
+    ProcessControlBlockInner::insert_object(&mut process_inner.condvar_list, condvar) as isize
+}
+
+/// 唤醒一个等待条件变量的线程
+pub fn sys_condvar_signal(condvar_id: usize) -> isize {
+    let process = current_process();
+    let process_inner = process.inner_exclusive_access();
+    let Some(Some(condvar)) = process_inner.condvar_list.get(condvar_id).cloned() else {
+        return -EINVAL;
+    };
+    drop(process_inner);
+    condvar.signal();
+    0
+}
+
+/// 释放互斥锁并等待条件变量，被唤醒后重新获取互斥锁；当前线程没有持有该锁时返回-EPERM
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    let current = current_task_handle();
+    let process = current_process();
+    let process_inner = process.inner_exclusive_access();
+    let (Some(Some(condvar)), Some(Some(mutex))) = (
+        process_inner.condvar_list.get(condvar_id).cloned(),
+        process_inner.mutex_list.get(mutex_id).cloned(),
+    ) else {
+        return -EINVAL;
+    };
+    if !mutex.is_held_by(current) {
+        return -EPERM;
+    }
+    drop(process_inner);
+    // 等待期间互斥锁被释放，返回前又重新获得
+    let tid = current_tid();
+    process.inner_exclusive_access().mutex_banker.release(tid, mutex_id);
+    condvar.wait(mutex);
+    process.inner_exclusive_access().mutex_banker.acquire(tid, mutex_id);
+    0
+}
+
+/// 开启（enabled为1）或关闭（enabled为0）当前进程的死锁检测
+pub fn sys_enable_deadlock_detect(enabled: usize) -> isize {
+    let process = current_process();
+    let mut process_inner = process.inner_exclusive_access();
+    match enabled {
+        0 => process_inner.deadlock_detect = false,
+        1 => process_inner.deadlock_detect = true,
+        _ => return -EINVAL,
+    }
+    0
+}
+
+const FUTEX_WAIT: usize = 0;
+const FUTEX_WAKE: usize = 1;
+
+/// futex操作：WAIT在*addr仍等于val时阻塞，否则返回-EAGAIN；WAKE唤醒最多val个等待者并返回唤醒的个数
+pub fn sys_futex(addr: usize, op: usize, val: usize) -> isize {
+    if addr % core::mem::size_of::<u32>() != 0 {
+        return -EINVAL;
+    }
+    let Some(pa) = translated_user_pa(current_user_token(), addr) else {
+        return -EFAULT;
+    };
+    match op {
+        FUTEX_WAIT => {
+            if futex_wait(pa.0, val as u32) {
+                0
+            } else {
+                -EAGAIN
+            }
+        }
+        FUTEX_WAKE => futex_wake(pa.0, val) as isize,
+        _ => -EINVAL,
+    }
+}
@@ -0,0 +1,25 @@
+//! 线程相关的系统调用
+
+use super::errno::{EINVAL, ESRCH};
+use crate::task::{create_thread, current_tid, wait_thread};
+
+/// 在当前进程中创建一个从entry开始执行的线程，arg作为其第一个参数，返回新线程的线程id
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    create_thread(entry, arg) as isize
+}
+
+/// 获取当前线程的线程id
+pub fn sys_gettid() -> isize {
+    current_tid() as isize
+}
+
+/// 等待线程tid退出并回收它，返回其退出码；等待自身时返回-EINVAL，线程不存在（或已被回收）时返回-ESRCH
+pub fn sys_waittid(tid: usize) -> isize {
+    if tid == current_tid() {
+        return -EINVAL;
+    }
+    match wait_thread(tid) {
+        Some(exit_code) => exit_code as isize,
+        None => -ESRCH,
+    }
+}
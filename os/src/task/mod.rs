@@ -4,36 +4,132 @@
 
 
 mod context;
+mod process;
+mod processor;
+mod scheduler;
+mod signal;
 mod switch;
+mod table;
 
 #[allow(clippy::module_inception)]
 mod task;
 
-use crate::loader::{get_app_data, get_num_app};
-use crate::sbi::shutdown;
-use crate::sync::UPSafeCell;
+use crate::console::{deliver_ctrl_c, poll_ctrl_c, push_foreground, remove_foreground};
+use crate::loader::{get_app_data, get_app_name, get_num_app};
+use crate::mm::handle_ipi;
+use crate::sbi::{send_ipi, shutdown};
+use crate::sync::{irq_restore, irq_save, SpinNoIrqLock, RANK_TASK_MANAGER};
+use crate::config::{
+    trap_cx_bottom_from_tid, ShutdownPolicy, INIT_TASK_ID, MAX_HARTS, SHUTDOWN_POLICY,
+};
+#[cfg(feature = "tickless")]
+use crate::timer::set_idle_trigger;
+use crate::timer::{check_timers, cycles_to_ms, get_time, set_next_trigger, timer_interrupts};
+use core::arch::asm;
+use riscv::register::sip;
 use lazy_static::*;
 use crate::trap::TrapContext;
 use self::switch::__switch;
+use self::table::TaskTable;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 
+pub use self::process::{ProcessControlBlock, ProcessControlBlockInner};
+pub use self::processor::hart_id;
+use self::processor::Processor;
+pub use self::table::TaskHandle;
 pub use self::task::{TaskControlBlock, TaskStatus};
 pub use self::context::TaskContext;
+pub use self::scheduler::{mlfq_test, DeadlineParams, Scheduler};
+pub use self::signal::{SignalAction, SignalFlags, SIG_BLOCK, SIG_SETMASK, SIG_UNBLOCK};
+use self::signal::{SignalFrame, SIG_DFL, SIG_IGN};
 
 /// 任务管理器
 pub struct TaskManager {
-    /// app总数
-    num_app: usize,
-    /// 使用互斥借用容器获取任务管理器的可变借用
-    inner: UPSafeCell<TaskManagerInner>,
+    /// 所有hart共享的任务管理器，时钟中断中也会访问，因此持锁时关中断
+    inner: SpinNoIrqLock<TaskManagerInner>,
 }
 
 /// 任务管理器内部
 pub struct TaskManagerInner {
-    /// TCB列表
-    tasks: Vec<TaskControlBlock>,
-    /// 当前运行的任务id
-    current_task: usize,
+    /// 任务表，下标为任务id，前num_app个为各应用的主线程；
+    /// TCB放在堆上，其他hart创建线程使任务表扩容时任务上下文的地址保持不变
+    tasks: TaskTable,
+    /// 每个hart的处理器状态，下标为hart id
+    processors: Vec<Processor>,
+    /// 调度器，保存所有就绪态任务的id，所有hart共享
+    scheduler: Box<dyn Scheduler>,
+    /// 实时（EDF）调度类，优先于普通调度器
+    deadline: scheduler::DeadlineClass,
+}
+
+impl TaskManagerInner {
+    /// 把就绪任务交给它所属的调度类
+    fn add_ready(&mut self, task_id: usize) {
+        if self.deadline.contains(task_id) {
+            self.deadline.add(task_id);
+        } else {
+            self.scheduler.add(task_id);
+        }
+    }
+    /// 取出下一个就绪任务，实时任务优先
+    fn fetch_ready(&mut self) -> Option<usize> {
+        self.deadline.fetch().or_else(|| self.scheduler.fetch())
+    }
+    /// 当前hart上正在运行的任务id
+    fn current_task(&self) -> usize {
+        self.processors[hart_id()]
+            .current
+            .expect("no task running on this hart")
+    }
+    /// 以exit_code结束进程中除except之外的所有线程，
+    /// 返回可以立即释放的任务，以及正在运行被结束线程、需要发送核间中断的hart
+    fn kill_threads(
+        &mut self,
+        process: &ProcessControlBlock,
+        except: Option<usize>,
+        exit_code: i32,
+    ) -> (Vec<Box<TaskControlBlock>>, usize) {
+        let mut released = Vec::new();
+        let mut running_harts = 0;
+        let mut process_inner = process.inner_exclusive_access();
+        process_inner.exited = true;
+        remove_foreground(process.pid);
+        for tid in 0..process_inner.threads.len() {
+            let Some(id) = process_inner.threads[tid] else {
+                continue;
+            };
+            if Some(id) == except {
+                continue;
+            }
+            let thread = &mut self.tasks[id];
+            if thread.task_status != TaskStatus::Exited {
+                thread.task_status = TaskStatus::Exited;
+                thread.exit_code = Some(exit_code);
+                self.scheduler.remove(id);
+                self.deadline.remove(id);
+            }
+            // 还在其他hart上运行的线程换下时由put_prev_task释放
+            if self.tasks[id].on_cpu {
+                if let Some(hart) = self
+                    .processors
+                    .iter()
+                    .position(|processor| processor.current == Some(id))
+                {
+                    running_harts |= 1 << hart;
+                }
+            } else {
+                process_inner.threads[tid] = None;
+                released.extend(self.tasks.remove(id));
+            }
+        }
+        if SHUTDOWN_POLICY == ShutdownPolicy::InitExits && process.pid == INIT_TASK_ID {
+            println!("Init task exited, shutdown!");
+            shutdown(false);
+        }
+        (released, running_harts)
+    }
 }
 
 lazy_static! {
@@ -42,135 +138,658 @@ lazy_static! {
         println!("init TASK_MANAGER");
         let num_app = get_num_app();
         println!("num_app = {}", num_app);
-        let mut tasks: Vec<TaskControlBlock> = Vec::new();
+        let mut tasks = TaskTable::new();
         for i in 0..num_app {
             println!("Begin load TCB{}", i);
-            tasks.push(TaskControlBlock::new(get_app_data(i), i));
+            let task_id = tasks.alloc_id();
+            tasks.insert(
+                task_id,
+                Box::new(TaskControlBlock::new(get_app_data(i), task_id, &[get_app_name(i)])),
+            );
             println!("End load TCB{}", i);
         }
         println!("Successfully initialize the TrakControlBlock Vector.");
+        let mut scheduler = scheduler::new_scheduler();
+        for i in 0..num_app {
+            scheduler.add(i);
+        }
         TaskManager {
-            num_app,
-            inner: unsafe {
-                UPSafeCell::new(TaskManagerInner {
+            inner: SpinNoIrqLock::new_ranked(
+                TaskManagerInner {
                     tasks,
-                    current_task: 0,
-                })
-            },
+                    processors: (0..MAX_HARTS).map(|_| Processor::new()).collect(),
+                    scheduler,
+                    deadline: scheduler::DeadlineClass::new(),
+                },
+                RANK_TASK_MANAGER,
+            ),
         }
     };
 }
 
 impl TaskManager {
-    /// 运行第一个任务
-    fn run_first_task(&self) -> ! {
-        let mut inner = self.inner.exclusive_access();
-        let task0 = &mut inner.tasks[0];
-        task0.task_status = TaskStatus::Running;
-        let next_task_cx_ptr = &task0.task_cx as *const TaskContext;
-        drop(inner);
-        let mut _unused = TaskContext::zero_init();
-        println!("Begin to run the first app.");
-        unsafe {
-            __switch(&mut _unused as *mut TaskContext, next_task_cx_ptr);
+    /// 空闲循环，每个hart运行在自己的启动栈上，任务让出CPU时总是先切换回这里
+    fn idle_loop(&self) -> ! {
+        let hart = hart_id();
+        println!("[kernel] hart {} begins to run apps.", hart);
+        loop {
+            check_timers();
+            deliver_ctrl_c();
+            let mut inner = self.inner.lock();
+            if let Some(next_task_id) = inner.fetch_ready() {
+                println!("task {} start on hart {}", next_task_id, hart);
+                let next = &mut inner.tasks[next_task_id];
+                next.task_status = TaskStatus::Running;
+                next.on_cpu = true;
+                next.time_stamp = get_time();
+                if next.tid == 0 {
+                    let mut process_inner = next.process.inner_exclusive_access();
+                    if !process_inner.started {
+                        process_inner.started = true;
+                        // 最近开始运行的进程成为前台进程，接收Ctrl-C
+                        push_foreground(next.process.pid);
+                    }
+                }
+                let next_task_cx_ptr = &next.task_cx as *const TaskContext;
+                let processor = &mut inner.processors[hart];
+                processor.current = Some(next_task_id);
+                let idle_task_cx_ptr = &mut processor.idle_task_cx as *mut TaskContext;
+                drop(inner);
+                unsafe {
+                    __switch(idle_task_cx_ptr, next_task_cx_ptr);
+                }
+                self.put_prev_task(hart);
+            } else if inner
+                .tasks
+                .iter()
+                .any(|task| task.task_status != TaskStatus::Exited)
+            {
+                // 剩下的任务在其他hart上运行、阻塞或被节流，等待时钟中断后重新检查
+                drop(inner);
+                wait_for_interrupt();
+            } else {
+                drop(inner);
+                println!("All applications completed, shutdown!");
+                println!("[kernel] {} timer interrupts taken", timer_interrupts());
+                shutdown(false);
+            }
+        }
+    }
+
+    /// 任务切换回空闲循环后调用，此时它的上下文已经保存完毕，才能交给其他hart运行
+    fn put_prev_task(&self, hart: usize) {
+        let mut inner = self.inner.lock();
+        let Some(prev) = inner.processors[hart].current.take() else {
+            return;
+        };
+        inner.tasks[prev].on_cpu = false;
+        inner.scheduler.put_prev(prev);
+        let status = inner.tasks[prev].task_status;
+        match status {
+            TaskStatus::Ready => inner.add_ready(prev),
+            TaskStatus::Exited => {
+                inner.scheduler.remove(prev);
+                let process = inner.tasks[prev].process.clone();
+                let tid = inner.tasks[prev].tid;
+                // 进程已经结束时没有线程会来回收，直接释放；否则留给waittid取走退出码
+                let mut process_inner = process.inner_exclusive_access();
+                let released = if process_inner.exited {
+                    process_inner.threads[tid] = None;
+                    inner.tasks.remove(prev)
+                } else {
+                    None
+                };
+                drop(process_inner);
+                drop(inner);
+                // 释放内核栈需要刷新所有hart的TLB，不在持有任务管理器的锁时进行
+                drop(released);
+                // 线程不再占用内核栈和Trap上下文，通知等待者回收
+                process.exit_waiters.lock().wake_all();
+            }
+            _ => {}
         }
-        panic!("unreachable in run_first_task!");
     }
 
-    /// 挂起当前任务
-    fn mark_current_suspended(&self) {
-        let mut inner = self.inner.exclusive_access();
-        let current_task_id = inner.current_task;
+    /// 挂起当前任务，voluntary表示是否是主动让出CPU
+    fn mark_current_suspended(&self, voluntary: bool) {
+        let mut inner = self.inner.lock();
+        let current_task_id = inner.current_task();
         println!("task {} suspended", current_task_id);
-        inner.tasks[current_task_id].task_status = TaskStatus::Ready;
+        let task = &mut inner.tasks[current_task_id];
+        // 任务可能已被其他hart上退出的主线程结束
+        if task.task_status == TaskStatus::Running {
+            task.task_status = TaskStatus::Ready;
+        }
+        if voluntary {
+            task.voluntary_switches += 1;
+        } else {
+            task.involuntary_switches += 1;
+        }
+    }
+
+    /// 阻塞当前任务，任务不进入调度器，直到被唤醒
+    fn mark_current_blocked(&self) {
+        let mut inner = self.inner.lock();
+        let current_task_id = inner.current_task();
+        let task = &mut inner.tasks[current_task_id];
+        if task.task_status == TaskStatus::Running {
+            if task.wakeup_pending {
+                // 阻塞前已经被唤醒，只让出一次CPU
+                task.wakeup_pending = false;
+                task.task_status = TaskStatus::Ready;
+            } else {
+                task.task_status = TaskStatus::Blocked;
+            }
+        }
+        task.voluntary_switches += 1;
+    }
+
+    /// 唤醒一个阻塞的任务，返回任务是否仍然存活；任务已被回收或已经退出时什么也不做
+    fn wakeup_task(&self, handle: TaskHandle) -> bool {
+        let mut inner = self.inner.lock();
+        let task_id = handle.id;
+        let Some(task) = inner.tasks.resolve(handle) else {
+            return false;
+        };
+        match task.task_status {
+            TaskStatus::Blocked => {
+                task.task_status = TaskStatus::Ready;
+                // 还没切换回空闲循环的任务由put_prev_task加入就绪队列
+                if !task.on_cpu {
+                    inner.add_ready(task_id);
+                }
+            }
+            // 任务已登记到等待队列，但还没来得及阻塞；登记之后可能先被抢占而处于就绪态
+            TaskStatus::Running | TaskStatus::Ready => task.wakeup_pending = true,
+            TaskStatus::Exited => return false,
+            _ => {}
+        }
+        true
+    }
+
+    /// 获取当前任务的id
+    fn get_current_task_id(&self) -> usize {
+        self.inner.lock().current_task()
+    }
+
+    /// 句柄对应的任务是否还没有退出
+    fn is_task_alive(&self, handle: TaskHandle) -> bool {
+        self.inner.lock().tasks.is_alive(handle)
+    }
+
+    /// 当前任务是否已经退出（被其他hart上退出的主线程结束）
+    fn is_current_exited(&self) -> bool {
+        let inner = self.inner.lock();
+        inner.tasks[inner.current_task()].task_status == TaskStatus::Exited
+    }
+
+    /// 获取当前任务的句柄
+    fn get_current_task_handle(&self) -> TaskHandle {
+        let inner = self.inner.lock();
+        inner.tasks.handle(inner.current_task())
+    }
+
+    /// 退出当前任务，主线程退出或exit_process为真时进程中的其他线程一并退出
+    fn mark_current_exited(&self, exit_code: i32, exit_process: bool) {
+        let mut inner = self.inner.lock();
+        let current_task_id = inner.current_task();
+        let task = &mut inner.tasks[current_task_id];
+        task.task_status = TaskStatus::Exited;
+        task.exit_code = Some(exit_code);
+        let now = get_time();
+        task.kernel_time += now - task.time_stamp;
+        task.time_stamp = now;
+        println!(
+            "task {} exited, user time = {}ms, kernel time = {}ms, switches = {} voluntary / {} involuntary",
+            current_task_id,
+            cycles_to_ms(task.user_time),
+            cycles_to_ms(task.kernel_time),
+            task.voluntary_switches,
+            task.involuntary_switches,
+        );
+        let tid = task.tid;
+        let process = task.process.clone();
+        if let Some(misses) = inner.deadline.remove(current_task_id) {
+            println!("[kernel] task {} missed {} deadlines", current_task_id, misses);
+        }
+        let (released, running_harts) = if exit_process || tid == 0 {
+            inner.kill_threads(&process, Some(current_task_id), exit_code)
+        } else {
+            (Vec::new(), 0)
+        };
+        drop(inner);
+        drop(released);
+        if running_harts != 0 {
+            send_ipi(running_harts);
+        }
+        // 等待线程退出的任务在put_prev_task中唤醒
+    }
+
+    /// 在当前进程中创建一个新线程，返回其线程id
+    fn create_thread(&self, entry: usize, arg: usize) -> usize {
+        let mut inner = self.inner.lock();
+        let process = inner.tasks[inner.current_task()].process.clone();
+        let task_id = inner.tasks.alloc_id();
+        let task = TaskControlBlock::new_thread(&process, task_id, entry, arg);
+        let tid = task.tid;
+        inner.tasks.insert(task_id, Box::new(task));
+        inner.add_ready(task_id);
+        tid
+    }
+
+    /// 回收当前进程中已退出的线程tid，返回其退出码；线程尚未退出时返回Ok(None)，
+    /// 线程不存在或是当前线程自身时返回Err(())
+    fn reap_thread(&self, tid: usize) -> Result<Option<i32>, ()> {
+        let mut inner = self.inner.lock();
+        let current = &inner.tasks[inner.current_task()];
+        if current.tid == tid {
+            return Err(());
+        }
+        let mut process_inner = current.process.inner_exclusive_access();
+        let task_id = process_inner.threads.get(tid).copied().flatten().ok_or(())?;
+        match inner.tasks[task_id].exit_code {
+            // 已退出但还在切换回空闲循环的线程仍在使用自己的内核栈，暂不回收
+            Some(exit_code) if !inner.tasks[task_id].on_cpu => {
+                process_inner.threads[tid] = None;
+                process_inner.dealloc_thread_resources(tid);
+                // 线程id可能被复用，清除它在死锁检测中的记录
+                process_inner.mutex_banker.remove_thread(tid);
+                process_inner.semaphore_banker.remove_thread(tid);
+                drop(process_inner);
+                // 回收任务id和内核栈
+                let released = inner.tasks.remove(task_id);
+                drop(inner);
+                drop(released);
+                Ok(Some(exit_code))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// 向进程pid发送信号，信号挂在它的主线程上；进程不存在或已退出时返回false。
+    /// 阻塞中的任务要等被唤醒、返回用户态时才会处理信号，但执行默认动作的致命信号直接结束整个进程。
+    /// 需要获取进程的锁，不能在中断处理中调用
+    fn send_signal(&self, pid: usize, signal: SignalFlags) -> bool {
+        let mut inner = self.inner.lock();
+        let Some(task) = inner.tasks.get_mut(pid) else {
+            return false;
+        };
+        if task.tid != 0 || task.process.pid != pid || task.task_status == TaskStatus::Exited {
+            return false;
+        }
+        task.signals.insert(signal);
+        // 阻塞的主线程要等被唤醒后才会处理信号，执行默认动作的致命信号不能一直等下去
+        if task.task_status != TaskStatus::Blocked {
+            return true;
+        }
+        let signum = signal.lowest_signum().unwrap();
+        let process = task.process.clone();
+        let fatal = SignalFlags::uncatchable().contains(signal)
+            || (!task.signal_mask.contains(signal)
+                && !SignalFlags::ignored_by_default().contains(signal)
+                && process.inner_exclusive_access().signal_actions.table[signum].handler
+                    == SIG_DFL);
+        if !fatal {
+            return true;
+        }
+        let (released, running_harts) = inner.kill_threads(&process, None, -(signum as i32));
+        drop(inner);
+        println!("[kernel] process {} killed by signal {}", pid, signum);
+        drop(released);
+        if running_harts != 0 {
+            send_ipi(running_harts);
+        }
+        true
+    }
+
+    /// 当前任务执行时触发了异常，向它自身产生信号
+    fn raise_current_signal(&self, signal: SignalFlags) {
+        let mut inner = self.inner.lock();
+        let current = inner.current_task();
+        inner.tasks[current].signals.insert(signal);
+    }
+
+    /// 获取当前任务的屏蔽信号集
+    fn get_current_signal_mask(&self) -> SignalFlags {
+        let inner = self.inner.lock();
+        inner.tasks[inner.current_task()].signal_mask
     }
 
-    /// 退出当前任务
-    fn mark_current_exited(&self) {
-        let mut inner = self.inner.exclusive_access();
-        let current_task_id = inner.current_task;
-        println!("task {} exited", current_task_id);
-        inner.tasks[current_task_id].task_status = TaskStatus::Exited;
+    /// 设置当前任务的屏蔽信号集，SIGKILL不能被屏蔽
+    fn set_current_signal_mask(&self, mask: SignalFlags) {
+        let mut inner = self.inner.lock();
+        let current = inner.current_task();
+        inner.tasks[current].signal_mask = mask - SignalFlags::uncatchable();
     }
 
-    /// 用Josephu环寻找下一个处于就绪态的任务
-    fn find_next_task(&self) -> Option<usize> {
-        let inner = self.inner.exclusive_access();
-        let current_task_id = inner.current_task;
-        (current_task_id + 1..current_task_id + self.num_app + 1)
-            .map(|id| id % self.num_app)
-            .find(|id| inner.tasks[*id].task_status == TaskStatus::Ready)
+    /// 返回用户态前处理当前任务未被屏蔽的待处理信号，需要结束任务时返回退出码
+    fn handle_current_signals(&self) -> Option<i32> {
+        let mut inner = self.inner.lock();
+        let current = inner.current_task();
+        let task = &mut inner.tasks[current];
+        let process = task.process.clone();
+        let process_inner = process.inner_exclusive_access();
+        loop {
+            // 同步异常信号无法推迟到解除屏蔽之后，总是参与递送
+            let deliverable = task.signals & (!task.signal_mask | SignalFlags::synchronous());
+            let signum = deliverable.lowest_signum()?;
+            let signal = SignalFlags::from_bits_retain(1 << signum);
+            task.signals.remove(signal);
+            let action = process_inner.signal_actions.table[signum];
+            // 返回故障指令会再次触发同一异常，无法处理时只能结束任务
+            let forced = SignalFlags::synchronous().contains(signal)
+                && (task.signal_mask.contains(signal)
+                    || task.signal_frame.is_some()
+                    || action.handler == SIG_IGN);
+            match action.handler {
+                _ if forced || SignalFlags::uncatchable().contains(signal) => {
+                    return Some(-(signum as i32));
+                }
+                SIG_IGN => {}
+                SIG_DFL if SignalFlags::ignored_by_default().contains(signal) => {}
+                SIG_DFL => return Some(-(signum as i32)),
+                handler => {
+                    if task.signal_frame.is_some() {
+                        // 正在执行其他信号的处理函数，留到sigreturn之后
+                        task.signals.insert(signal);
+                        return None;
+                    }
+                    let trap_cx = task.get_trap_cx();
+                    task.signal_frame = Some(SignalFrame {
+                        trap_cx: *trap_cx,
+                        mask: task.signal_mask,
+                    });
+                    task.signal_mask |= action.mask | signal;
+                    task.signal_mask -= SignalFlags::uncatchable();
+                    trap_cx.sepc = handler;
+                    trap_cx.x[10] = signum;
+                    // 处理函数返回时进入restorer，由它调用sigreturn
+                    trap_cx.x[1] = action.restorer;
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// 从信号处理函数返回，恢复进入处理函数前的现场，返回恢复后的a0；没有在处理信号时返回None
+    fn restore_current_signal_frame(&self) -> Option<usize> {
+        let mut inner = self.inner.lock();
+        let current = inner.current_task();
+        let task = &mut inner.tasks[current];
+        let frame = task.signal_frame.take()?;
+        task.signal_mask = frame.mask;
+        let trap_cx = task.get_trap_cx();
+        *trap_cx = frame.trap_cx;
+        Some(trap_cx.x[10])
+    }
+
+    /// 获取当前任务所属的进程
+    fn get_current_process(&self) -> Arc<ProcessControlBlock> {
+        let inner = self.inner.lock();
+        inner.tasks[inner.current_task()].process.clone()
+    }
+
+    /// 获取当前任务的线程id
+    fn get_current_tid(&self) -> usize {
+        let inner = self.inner.lock();
+        inner.tasks[inner.current_task()].tid
+    }
+
+    /// 是否需要时间片：有其他就绪任务需要轮转，或者有实时任务需要记账预算
+    #[cfg(feature = "tickless")]
+    fn need_time_slice(&self) -> bool {
+        let inner = self.inner.lock();
+        !inner.deadline.is_empty() || inner.scheduler.nr_ready() > 0
+    }
+
+    /// 时钟中断时通知调度器，返回是否需要抢占当前任务
+    fn scheduler_tick(&self) -> bool {
+        let mut inner = self.inner.lock();
+        let current = inner.current_task();
+        let preempt = inner.deadline.on_tick(current);
+        if inner.deadline.contains(current) {
+            preempt
+        } else {
+            // 普通调度器的记账不能因实时任务的抢占而跳过
+            inner.scheduler.on_tick(current) || preempt
+        }
+    }
+
+    /// 将当前任务设为实时任务，未通过准入控制时返回false
+    fn set_current_deadline(&self, params: DeadlineParams) -> bool {
+        let mut inner = self.inner.lock();
+        let current = inner.current_task();
+        if !inner.deadline.admit(current, params) {
+            return false;
+        }
+        inner.scheduler.remove(current);
+        true
     }
-    
+
+    /// 获取当前任务的实时参数及错过截止期限的次数
+    fn get_current_deadline(&self) -> Option<(DeadlineParams, usize)> {
+        let inner = self.inner.lock();
+        inner.deadline.get_attr(inner.current_task())
+    }
+
+    /// 调整当前任务的nice值，返回调整后的nice值
+    fn change_current_nice(&self, increment: isize) -> isize {
+        let mut inner = self.inner.lock();
+        let current = inner.current_task();
+        let nice = inner.tasks[current].nice.saturating_add(increment).clamp(-20, 19);
+        inner.tasks[current].nice = nice;
+        inner.scheduler.set_nice(current, nice);
+        nice
+    }
+
+    /// 获取调度策略给出的下一个时间片长度
+    fn get_time_slice(&self) -> usize {
+        self.inner.lock().scheduler.time_slice()
+    }
+
+    /// 设置当前任务的优先级
+    fn set_current_priority(&self, priority: usize) {
+        let mut inner = self.inner.lock();
+        let current = inner.current_task();
+        inner.scheduler.set_priority(current, priority);
+    }
+
+    /// 从内核态返回用户态前调用，结算当前任务的内核态时间
+    fn user_time_start(&self) {
+        let mut inner = self.inner.lock();
+        let current = inner.current_task();
+        let task = &mut inner.tasks[current];
+        let now = get_time();
+        task.kernel_time += now - task.time_stamp;
+        task.time_stamp = now;
+    }
+
+    /// 从用户态进入内核态时调用，结算当前任务的用户态时间
+    fn user_time_end(&self) {
+        let mut inner = self.inner.lock();
+        let current = inner.current_task();
+        let task = &mut inner.tasks[current];
+        let now = get_time();
+        task.user_time += now - task.time_stamp;
+        task.time_stamp = now;
+    }
+
+    /// 获取当前任务的(用户态时间, 内核态时间, 主动切换次数, 被动切换次数)，时间单位为时钟周期
+    fn get_current_usage(&self) -> (usize, usize, usize, usize) {
+        let mut inner = self.inner.lock();
+        let current = inner.current_task();
+        let task = &mut inner.tasks[current];
+        let now = get_time();
+        task.kernel_time += now - task.time_stamp;
+        task.time_stamp = now;
+        (
+            task.user_time,
+            task.kernel_time,
+            task.voluntary_switches,
+            task.involuntary_switches,
+        )
+    }
+
     /// 获取当前正在运行的应用程序地址空间的token
     fn get_current_token(&self) -> usize {
-        let inner = self.inner.exclusive_access();
-        inner.tasks[inner.current_task].get_user_token()
+        let inner = self.inner.lock();
+        let mut process_inner = inner.tasks[inner.current_task()].process.inner_exclusive_access();
+        // 返回用户态前确认ASID仍属于当前代
+        process_inner.memory_set.refresh_asid();
+        process_inner.get_user_token()
     }
 
     /// 获取当前正在运行的应用程序的TrapContext可变引用
     fn get_current_trap_cx(&self) -> &'static mut TrapContext {
-        let inner = self.inner.exclusive_access();
-        inner.tasks[inner.current_task].get_trap_cx()
+        let inner = self.inner.lock();
+        inner.tasks[inner.current_task()].get_trap_cx()
     }
 
     /// 改变当前正在运行应用程序的program break
     pub fn change_current_program_brk(&self, size: i32) -> Option<usize> {
-        let mut inner = self.inner.exclusive_access();
-        let cur = inner.current_task;
-        inner.tasks[cur].change_program_brk(size)
+        let inner = self.inner.lock();
+        let cur = inner.current_task();
+        let mut process_inner = inner.tasks[cur].process.inner_exclusive_access();
+        process_inner.change_program_brk(size)
     }
 
-    /// 运行下一个就绪态任务
+    /// 切换回当前hart的空闲循环，由它选出下一个任务，调用前需关中断
     fn run_next_task(&self) {
-        if let Some(next_task_id) = self.find_next_task() {
-            let mut inner = self.inner.exclusive_access();
-            let current = inner.current_task;
-            println!("task {} start", current);
-            inner.tasks[next_task_id].task_status = TaskStatus::Running;
-            inner.current_task = next_task_id;
-            let current_task_cx_ptr = &mut inner.tasks[current].task_cx as *mut TaskContext;
-            let next_task_cx_ptr = &inner.tasks[next_task_id].task_cx as *const TaskContext;
-            drop(inner);
-            unsafe {
-                __switch(current_task_cx_ptr, next_task_cx_ptr);
-            }
-        } else {
-            println!("All applications completed, shutdown!");
-            shutdown(false);
+        let mut inner = self.inner.lock();
+        let hart = hart_id();
+        let current = inner.current_task();
+        // 切换前结算当前任务的内核态时间
+        let task = &mut inner.tasks[current];
+        task.kernel_time += get_time() - task.time_stamp;
+        let current_task_cx_ptr = &mut task.task_cx as *mut TaskContext;
+        let idle_task_cx_ptr = &inner.processors[hart].idle_task_cx as *const TaskContext;
+        drop(inner);
+        unsafe {
+            __switch(current_task_cx_ptr, idle_task_cx_ptr);
         }
     }
 }
 
-/// 运行第一个任务
+/// 等待中断：时钟中断未在sstatus中使能，wfi在sie中使能的中断到来时返回，
+/// 由空闲循环轮询处理，不进入trap
+fn wait_for_interrupt() {
+    // 无滴答模式下时钟可能没有设置，等待前按最近的定时器重新设置
+    #[cfg(feature = "tickless")]
+    set_idle_trigger();
+    unsafe {
+        asm!("wfi");
+    }
+    // 可能是其他hart发来的TLB刷新请求
+    handle_ipi();
+    if sip::read().stimer() {
+        // 空闲的hart也要轮询控制台，其他hart上的任务可能独占CPU而没有时钟中断
+        poll_ctrl_c();
+        // 重新设置mtimecmp以清除挂起的时钟中断
+        set_next_trigger();
+    }
+}
+
+/// 初始化任务管理器，需在启动其他hart之前调用
+pub fn init() {
+    lazy_static::initialize(&TASK_MANAGER);
+}
+
+/// 当前hart进入空闲循环，开始运行任务
 pub fn run_first_task() {
-    TASK_MANAGER.run_first_task();
+    TASK_MANAGER.idle_loop();
 }
 
-/// 运行下一个任务
+/// 运行下一个任务，调用前需关中断：从标记任务状态到切换回空闲循环之间不能被时钟中断抢占，
+/// 否则抢占会在状态已经改变的任务上再做一次切换
 fn run_next_task() {
     TASK_MANAGER.run_next_task();
 }
 
 /// 挂起当前任务
-fn mark_current_suspended() {
-    TASK_MANAGER.mark_current_suspended();
+fn mark_current_suspended(voluntary: bool) {
+    TASK_MANAGER.mark_current_suspended(voluntary);
 }
 
 /// 退出当前任务
-fn mark_current_exited() {
-    TASK_MANAGER.mark_current_exited();
+fn mark_current_exited(exit_code: i32, exit_process: bool) {
+    TASK_MANAGER.mark_current_exited(exit_code, exit_process);
 }
 
-/// 挂起当前任务，并运行下一个任务
+/// 挂起当前任务（主动让出CPU），并运行下一个任务
 pub fn suspend_current_and_run_next() {
-    mark_current_suspended();
+    // 空闲循环总是关中断运行；中断状态属于任务自己，切换回来后恢复，此时可能已在其他hart上
+    let sie = irq_save();
+    mark_current_suspended(true);
+    run_next_task();
+    irq_restore(sie);
+}
+
+/// 抢占当前任务，并运行下一个任务
+pub fn preempt_current_and_run_next() {
+    let sie = irq_save();
+    mark_current_suspended(false);
+    run_next_task();
+    irq_restore(sie);
+}
+
+/// 阻塞当前任务，并运行下一个任务，调用前需把当前任务登记到睡眠队列或等待队列中
+pub fn block_current_and_run_next() {
+    let sie = irq_save();
+    TASK_MANAGER.mark_current_blocked();
+    run_next_task();
+    irq_restore(sie);
+}
+
+/// 唤醒一个阻塞的任务，使其重新进入调度器；任务已经退出时返回false
+pub fn wakeup_task(handle: TaskHandle) -> bool {
+    let woken = TASK_MANAGER.wakeup_task(handle);
+    // 当前任务可能正独占CPU而没有设置时间片，需要重新设置时钟中断
+    #[cfg(feature = "tickless")]
+    set_next_trigger();
+    woken
+}
+
+/// 除当前任务外是否还有需要轮转的任务，无滴答模式据此决定是否设置时间片
+#[cfg(feature = "tickless")]
+pub fn need_time_slice() -> bool {
+    TASK_MANAGER.need_time_slice()
+}
+
+/// 获取当前任务的id
+pub fn current_task_id() -> usize {
+    TASK_MANAGER.get_current_task_id()
+}
+
+/// 获取当前任务的句柄，用于稍后唤醒它
+pub fn current_task_handle() -> TaskHandle {
+    TASK_MANAGER.get_current_task_handle()
+}
+
+/// 句柄对应的任务是否还没有退出
+pub fn task_alive(handle: TaskHandle) -> bool {
+    TASK_MANAGER.is_task_alive(handle)
+}
+
+/// 当前线程已被其他hart上退出的主线程结束时切换走，不再返回用户态
+pub fn exit_if_killed() {
+    if TASK_MANAGER.is_current_exited() {
+        irq_save();
+        run_next_task();
+    }
+}
+
+/// 以exit_code退出当前任务，并运行下一个任务
+pub fn exit_current_and_run_next(exit_code: i32) {
+    // 已退出的任务不会再被切换回来，不需要恢复中断状态
+    irq_save();
+    mark_current_exited(exit_code, false);
     run_next_task();
 }
 
-/// 退出当前任务，并运行下一个任务
-pub fn exit_current_and_run_next() {
-    mark_current_exited();
+/// 以exit_code结束当前线程所在的整个进程，并运行下一个任务
+pub fn exit_current_process_and_run_next(exit_code: i32) {
+    irq_save();
+    mark_current_exited(exit_code, true);
     run_next_task();
 }
 /// 获取当前正在运行的应用程序地址空间的token
@@ -183,7 +802,128 @@ pub fn current_trap_cx() -> &'static mut TrapContext {
     TASK_MANAGER.get_current_trap_cx()
 }
 
+/// 获取当前线程的Trap上下文在用户地址空间中的虚拟地址
+pub fn current_trap_cx_user_va() -> usize {
+    trap_cx_bottom_from_tid(TASK_MANAGER.get_current_tid())
+}
+
+/// 获取当前任务所属的进程
+pub fn current_process() -> Arc<ProcessControlBlock> {
+    TASK_MANAGER.get_current_process()
+}
+
+/// 获取当前线程在进程内的线程id
+pub fn current_tid() -> usize {
+    TASK_MANAGER.get_current_tid()
+}
+
+/// 在当前进程中创建一个从entry开始执行的新线程，返回其线程id
+pub fn create_thread(entry: usize, arg: usize) -> usize {
+    TASK_MANAGER.create_thread(entry, arg)
+}
+
+/// 等待当前进程中的线程tid退出并回收它，返回其退出码；线程不存在或是当前线程自身时返回None
+pub fn wait_thread(tid: usize) -> Option<i32> {
+    let process = current_process();
+    loop {
+        // 持有等待队列的锁检查，避免线程在检查之后、登记之前退出而错过唤醒
+        let mut exit_waiters = process.exit_waiters.lock();
+        match TASK_MANAGER.reap_thread(tid) {
+            Ok(Some(exit_code)) => return Some(exit_code),
+            Ok(None) => {
+                exit_waiters.add_current();
+                drop(exit_waiters);
+                block_current_and_run_next();
+            }
+            Err(()) => return None,
+        }
+    }
+}
+
+/// 向进程pid发送信号，进程不存在或已退出时返回false；只能在线程上下文中调用，
+/// 中断处理中收到的信号（例如Ctrl-C）先记录下来，返回用户态前再发送
+pub fn send_signal(pid: usize, signal: SignalFlags) -> bool {
+    TASK_MANAGER.send_signal(pid, signal)
+}
+
+/// 当前任务执行时触发了异常，向它自身产生信号
+pub fn raise_current_signal(signal: SignalFlags) {
+    TASK_MANAGER.raise_current_signal(signal);
+}
+
+/// 获取当前任务的屏蔽信号集
+pub fn current_signal_mask() -> SignalFlags {
+    TASK_MANAGER.get_current_signal_mask()
+}
+
+/// 设置当前任务的屏蔽信号集
+pub fn set_current_signal_mask(mask: SignalFlags) {
+    TASK_MANAGER.set_current_signal_mask(mask);
+}
+
+/// 返回用户态前处理当前任务的信号，执行默认动作时结束整个进程
+pub fn handle_signals() {
+    if let Some(exit_code) = TASK_MANAGER.handle_current_signals() {
+        println!(
+            "[kernel] process {} killed by signal {}",
+            current_process().pid,
+            -exit_code
+        );
+        exit_current_process_and_run_next(exit_code);
+    }
+}
+
+/// 从信号处理函数返回，返回恢复后的a0；当前任务没有在处理信号时返回None
+pub fn signal_return() -> Option<usize> {
+    TASK_MANAGER.restore_current_signal_frame()
+}
+
 /// 改变当前正在运行应用程序的program break
 pub fn change_program_brk(size: i32) -> Option<usize> {
     TASK_MANAGER.change_current_program_brk(size)
 }
+
+/// 时钟中断时通知调度器，返回是否需要抢占当前任务
+pub fn scheduler_tick() -> bool {
+    TASK_MANAGER.scheduler_tick()
+}
+
+/// 设置当前任务的优先级
+pub fn set_current_priority(priority: usize) {
+    TASK_MANAGER.set_current_priority(priority);
+}
+
+/// 调整当前任务的nice值，返回调整后的nice值
+pub fn change_current_nice(increment: isize) -> isize {
+    TASK_MANAGER.change_current_nice(increment)
+}
+
+/// 获取调度策略给出的下一个时间片长度（时钟周期数）
+pub fn current_time_slice() -> usize {
+    TASK_MANAGER.get_time_slice()
+}
+
+/// 将当前任务设为实时任务，未通过准入控制时返回false
+pub fn set_current_deadline(params: DeadlineParams) -> bool {
+    TASK_MANAGER.set_current_deadline(params)
+}
+
+/// 获取当前任务的实时参数及错过截止期限的次数，普通任务返回None
+pub fn get_current_deadline() -> Option<(DeadlineParams, usize)> {
+    TASK_MANAGER.get_current_deadline()
+}
+
+/// 从内核态返回用户态前结算内核态时间
+pub fn user_time_start() {
+    TASK_MANAGER.user_time_start();
+}
+
+/// 从用户态进入内核态时结算用户态时间
+pub fn user_time_end() {
+    TASK_MANAGER.user_time_end();
+}
+
+/// 获取当前任务的(用户态时间, 内核态时间, 主动切换次数, 被动切换次数)，时间单位为时钟周期
+pub fn current_usage() -> (usize, usize, usize, usize) {
+    TASK_MANAGER.get_current_usage()
+}
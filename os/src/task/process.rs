@@ -0,0 +1,163 @@
+//! 进程控制块
+//!
+//! 同一进程中的所有线程共享地址空间、堆和mmap区域，
+//! 每个线程拥有自己的用户栈、Trap上下文页和内核栈。
+
+use super::signal::SignalActions;
+use crate::config::{thread_stack_position, trap_cx_bottom_from_tid, PAGE_SIZE};
+use crate::mm::{MapPermission, MemorySet, PhysPageNum, VirtAddr};
+use crate::sync::{Banker, Condvar, Mutex, Semaphore, SpinLock, SpinLockGuard, WaitQueue};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// 进程控制块
+pub struct ProcessControlBlock {
+    /// 进程id，即主线程的任务id
+    pub pid: usize,
+    /// 等待本进程中线程退出的任务，单独加锁以便唤醒时不持有进程内部的锁
+    pub exit_waiters: SpinLock<WaitQueue>,
+    inner: SpinLock<ProcessControlBlockInner>,
+}
+
+/// 进程控制块内部
+pub struct ProcessControlBlockInner {
+    /// 用户地址空间
+    pub memory_set: MemorySet,
+    /// 应用数据大小，从0x0到主线程用户栈结束地址的大小
+    pub base_size: usize,
+    /// 堆底地址
+    pub heap_bottom: usize,
+    /// 堆顶地址
+    pub program_brk: usize,
+    /// mmap区域的基址，线程栈从这里向下排列
+    pub mmap_base: usize,
+    /// 线程id -> 任务id，线程被回收后为None
+    pub threads: Vec<Option<usize>>,
+    /// 互斥锁列表，下标为互斥锁id
+    pub mutex_list: Vec<Option<Arc<dyn Mutex>>>,
+    /// 信号量列表，下标为信号量id
+    pub semaphore_list: Vec<Option<Arc<Semaphore>>>,
+    /// 条件变量列表，下标为条件变量id
+    pub condvar_list: Vec<Option<Arc<Condvar>>>,
+    /// 是否开启死锁检测
+    pub deadlock_detect: bool,
+    /// 互斥锁的银行家算法状态
+    pub mutex_banker: Banker,
+    /// 信号量的银行家算法状态
+    pub semaphore_banker: Banker,
+    /// 信号处理动作，进程内的所有线程共享
+    pub signal_actions: SignalActions,
+    /// 主线程已经退出，进程中的所有线程都已结束，退出的线程不再等待回收
+    pub exited: bool,
+    /// 主线程是否已经开始运行
+    pub started: bool,
+}
+
+impl ProcessControlBlock {
+    /// 由应用的地址空间创建进程，此时还没有任何线程
+    pub fn new(pid: usize, memory_set: MemorySet, base_size: usize, heap_bottom: usize) -> Self {
+        let mmap_base = memory_set.mmap_base();
+        Self {
+            pid,
+            exit_waiters: SpinLock::new(WaitQueue::new()),
+            inner: SpinLock::new(ProcessControlBlockInner {
+                memory_set,
+                base_size,
+                heap_bottom,
+                program_brk: heap_bottom,
+                mmap_base,
+                threads: Vec::new(),
+                mutex_list: Vec::new(),
+                semaphore_list: Vec::new(),
+                condvar_list: Vec::new(),
+                deadlock_detect: false,
+                mutex_banker: Banker::new(),
+                semaphore_banker: Banker::new(),
+                signal_actions: SignalActions::new(),
+                exited: false,
+                started: false,
+            }),
+        }
+    }
+    /// 获取进程控制块内部数据的锁
+    pub fn inner_exclusive_access(&self) -> SpinLockGuard<'_, ProcessControlBlockInner> {
+        self.inner.lock()
+    }
+}
+
+impl ProcessControlBlockInner {
+    /// 获取进程地址空间的token
+    pub fn get_user_token(&self) -> usize {
+        self.memory_set.token()
+    }
+    /// 分配一个线程id，优先复用已回收的线程id
+    pub fn alloc_tid(&mut self, task_id: usize) -> usize {
+        if let Some(tid) = self.threads.iter().position(|thread| thread.is_none()) {
+            self.threads[tid] = Some(task_id);
+            tid
+        } else {
+            self.threads.push(Some(task_id));
+            self.threads.len() - 1
+        }
+    }
+    /// 为线程tid（tid >= 1）映射用户栈和Trap上下文页，返回用户栈顶和Trap上下文的物理页号
+    pub fn alloc_thread_resources(&mut self, tid: usize) -> (usize, PhysPageNum) {
+        let (ustack_bottom, ustack_top) = thread_stack_position(self.mmap_base, tid);
+        self.memory_set.insert_framed_area(
+            ustack_bottom.into(),
+            ustack_top.into(),
+            MapPermission::R | MapPermission::W | MapPermission::U,
+        );
+        let trap_cx_bottom = trap_cx_bottom_from_tid(tid);
+        self.memory_set.insert_framed_area(
+            trap_cx_bottom.into(),
+            (trap_cx_bottom + PAGE_SIZE).into(),
+            MapPermission::R | MapPermission::W,
+        );
+        let trap_cx_ppn = self
+            .memory_set
+            .translate(VirtAddr::from(trap_cx_bottom).into())
+            .unwrap()
+            .ppn();
+        (ustack_top, trap_cx_ppn)
+    }
+    /// 回收线程tid的用户栈和Trap上下文页
+    pub fn dealloc_thread_resources(&mut self, tid: usize) {
+        let (ustack_bottom, _) = thread_stack_position(self.mmap_base, tid);
+        self.memory_set
+            .remove_area_with_start_vpn(VirtAddr::from(ustack_bottom).into());
+        self.memory_set
+            .remove_area_with_start_vpn(VirtAddr::from(trap_cx_bottom_from_tid(tid)).into());
+    }
+    /// 把obj放入list的第一个空位，返回其下标作为id
+    pub fn insert_object<T: ?Sized>(list: &mut Vec<Option<Arc<T>>>, obj: Arc<T>) -> usize {
+        if let Some(id) = list.iter().position(|item| item.is_none()) {
+            list[id] = Some(obj);
+            id
+        } else {
+            list.push(Some(obj));
+            list.len() - 1
+        }
+    }
+    /// change the location of the program break. return None if failed.
+    pub fn change_program_brk(&mut self, size: i32) -> Option<usize> {
+        let old_break = self.program_brk;
+        let new_brk = self.program_brk as isize + size as isize;
+        if new_brk < self.heap_bottom as isize {
+            return None;
+        }
+        let result = if size < 0 {
+            self.memory_set
+                .shrink_to(VirtAddr(self.heap_bottom), VirtAddr(new_brk as usize))
+        } else {
+            self.memory_set
+                .append_to(VirtAddr(self.heap_bottom), VirtAddr(new_brk as usize))
+        };
+        if result {
+            self.program_brk = new_brk as usize;
+            Some(old_break)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! 处理器（hart）状态
+//!
+//! 每个hart有自己的空闲循环和当前任务，就绪队列由所有hart共享。
+//! 任务切换总是先切回本hart的空闲循环，由空闲循环选出下一个任务，
+//! 这样被换下的任务在上下文保存完毕后才会重新进入就绪队列。
+
+use super::TaskContext;
+use core::arch::asm;
+
+/// 每个hart的处理器状态
+pub struct Processor {
+    /// 当前在该hart上运行的任务id
+    pub current: Option<usize>,
+    /// 该hart空闲循环的任务上下文
+    pub idle_task_cx: TaskContext,
+}
+
+impl Processor {
+    /// 创建一个空闲的处理器
+    pub fn new() -> Self {
+        Self {
+            current: None,
+            idle_task_cx: TaskContext::zero_init(),
+        }
+    }
+}
+
+/// 当前hart的id，内核中tp寄存器始终保存hart id
+pub fn hart_id() -> usize {
+    let id: usize;
+    unsafe {
+        asm!("mv {}, tp", out(reg) id);
+    }
+    id
+}
@@ -0,0 +1,111 @@
+//! 完全公平调度（CFS）
+//!
+//! 每个任务记录按权重折算后的虚拟运行时间vruntime，调度器总是选择vruntime最小的任务。
+//! 权重由nice值决定，时间片由就绪任务数决定：所有任务在一个调度周期内至少各运行一次。
+
+use super::Scheduler;
+use crate::config::CLOCK_FREQ;
+use crate::timer::get_time;
+use alloc::collections::{BTreeMap, BTreeSet};
+
+/// nice为0时的权重
+const NICE_0_WEIGHT: u64 = 1024;
+/// nice值[-20, 19]到权重的映射，与Linux相同，相邻nice值的CPU份额相差约10%
+const NICE_TO_WEIGHT: [u64; 40] = [
+    88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100, 4904,
+    3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172, 137, 110,
+    87, 70, 56, 45, 36, 29, 23, 18, 15,
+];
+/// 调度周期（毫秒）
+const SCHED_LATENCY_MS: usize = 20;
+/// 最小时间片（毫秒），就绪任务过多时调度周期随之延长
+const MIN_GRANULARITY_MS: usize = 2;
+
+/// 每个任务的调度信息
+struct CfsEntry {
+    vruntime: u64,
+    weight: u64,
+}
+
+/// 完全公平调度器
+pub struct CfsScheduler {
+    /// 按(vruntime, 任务id)排序的就绪任务
+    timeline: BTreeSet<(u64, usize)>,
+    entries: BTreeMap<usize, CfsEntry>,
+    /// 单调不减的最小vruntime，新任务和被唤醒的任务从这里开始
+    min_vruntime: u64,
+    /// 正在各个hart上运行的任务及其开始运行的时间
+    running: BTreeMap<usize, usize>,
+}
+
+impl CfsScheduler {
+    /// 创建一个空的完全公平调度器
+    pub fn new() -> Self {
+        Self {
+            timeline: BTreeSet::new(),
+            entries: BTreeMap::new(),
+            min_vruntime: 0,
+            running: BTreeMap::new(),
+        }
+    }
+    /// 把正在运行的任务自开始运行以来的时间折算进它的vruntime
+    fn charge_running(&mut self, task_id: usize) {
+        if let Some(start) = self.running.remove(&task_id) {
+            let delta = (get_time() - start) as u64;
+            if let Some(entry) = self.entries.get_mut(&task_id) {
+                entry.vruntime += delta * NICE_0_WEIGHT / entry.weight;
+            }
+        }
+    }
+}
+
+impl Scheduler for CfsScheduler {
+    fn add(&mut self, task_id: usize) {
+        self.charge_running(task_id);
+        let min_vruntime = self.min_vruntime;
+        let entry = self.entries.entry(task_id).or_insert(CfsEntry {
+            vruntime: min_vruntime,
+            weight: NICE_0_WEIGHT,
+        });
+        // 长时间睡眠的任务不能积累过多的运行时间优势
+        entry.vruntime = entry.vruntime.max(min_vruntime);
+        self.timeline.insert((entry.vruntime, task_id));
+    }
+    fn fetch(&mut self) -> Option<usize> {
+        let (vruntime, task_id) = self.timeline.pop_first()?;
+        self.min_vruntime = self.min_vruntime.max(vruntime);
+        self.running.insert(task_id, get_time());
+        Some(task_id)
+    }
+    fn remove(&mut self, task_id: usize) {
+        self.charge_running(task_id);
+        if let Some(entry) = self.entries.remove(&task_id) {
+            self.timeline.remove(&(entry.vruntime, task_id));
+        }
+    }
+    fn put_prev(&mut self, task_id: usize) {
+        // 阻塞的任务不会马上重新加入，在离开CPU时就结算，睡眠的时间不计入
+        self.charge_running(task_id);
+    }
+    fn nr_ready(&self) -> usize {
+        self.timeline.len()
+    }
+    fn on_tick(&mut self, task_id: usize) -> bool {
+        match self.running.get(&task_id) {
+            Some(&start) => get_time() - start >= self.time_slice(),
+            None => true,
+        }
+    }
+    fn set_nice(&mut self, task_id: usize, nice: isize) {
+        let weight = NICE_TO_WEIGHT[(nice + 20) as usize];
+        if let Some(entry) = self.entries.get_mut(&task_id) {
+            entry.weight = weight;
+        }
+    }
+    fn time_slice(&self) -> usize {
+        // 加上正在运行的任务
+        let nr_running = self.timeline.len() + 1;
+        let slice_ms = (SCHED_LATENCY_MS / nr_running).max(MIN_GRANULARITY_MS);
+        slice_ms * CLOCK_FREQ / 1000
+    }
+}
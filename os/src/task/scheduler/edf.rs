@@ -0,0 +1,244 @@
+//! 实时截止期限（EDF）调度类
+//!
+//! 任务通过系统调用声明(runtime, deadline, period)：每个周期开始时释放一个作业，
+//! 作业最多运行runtime，需要在周期开始后deadline之内完成（任务主动让出即表示作业完成）。
+//! 1. 准入控制：所有实时任务的利用率之和 sum(runtime / period) 不能超过1
+//! 2. 总是运行绝对截止期限最早的就绪作业，并且实时任务总是优先于普通任务
+//! 3. 作业用完预算或完成后被节流，直到下一个周期开始
+//! 4. 作业在截止期限到达时仍未完成，记为一次错过截止期限
+
+use crate::config::CLOCK_FREQ;
+use crate::timer::get_time;
+use alloc::collections::{BTreeMap, BTreeSet};
+
+/// 利用率的定点数精度，UTIL_SCALE表示利用率为1
+const UTIL_SCALE: usize = 1_000_000;
+
+/// 实时任务声明的参数，单位为毫秒
+#[derive(Copy, Clone)]
+pub struct DeadlineParams {
+    /// 每个周期内的最长运行时间
+    pub runtime: usize,
+    /// 相对截止期限
+    pub deadline: usize,
+    /// 周期
+    pub period: usize,
+}
+
+/// 每个实时任务的调度信息，时间单位为时钟周期
+struct DlEntry {
+    params: DeadlineParams,
+    runtime: usize,
+    deadline: usize,
+    period: usize,
+    util: usize,
+    /// 当前作业的绝对截止期限
+    abs_deadline: usize,
+    /// 当前作业剩余的预算
+    remaining: usize,
+    /// 下一个周期的开始时间
+    next_period: usize,
+    /// 是否被节流（等待下一个周期）
+    throttled: bool,
+    /// 当前作业是否已经完成
+    job_done: bool,
+    /// 当前作业是否已经记为错过截止期限
+    missed: bool,
+    /// 是否是被抢占的（而不是主动让出）
+    preempted: bool,
+    /// 错过截止期限的次数
+    misses: usize,
+}
+
+impl DlEntry {
+    /// 若当前作业已过截止期限仍未完成，记一次错过
+    fn check_miss(&mut self, now: usize) {
+        if !self.job_done && !self.missed && now > self.abs_deadline {
+            self.missed = true;
+            self.misses += 1;
+        }
+    }
+}
+
+/// EDF调度类
+pub struct DeadlineClass {
+    entries: BTreeMap<usize, DlEntry>,
+    /// 按(绝对截止期限, 任务id)排序的就绪作业
+    ready: BTreeSet<(usize, usize)>,
+    /// 已准入任务的利用率之和
+    total_util: usize,
+    /// 正在各个hart上运行的实时任务及其上次记账的时间
+    running: BTreeMap<usize, usize>,
+}
+
+/// 毫秒数换算为时钟周期数，溢出时返回None
+fn ms_to_cycles(ms: usize) -> Option<usize> {
+    ms.checked_mul(CLOCK_FREQ / 1000)
+}
+
+impl DeadlineClass {
+    /// 创建一个空的EDF调度类
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            ready: BTreeSet::new(),
+            total_util: 0,
+            running: BTreeMap::new(),
+        }
+    }
+    /// 判断任务是否属于实时调度类
+    pub fn contains(&self, task_id: usize) -> bool {
+        self.entries.contains_key(&task_id)
+    }
+    /// 是否没有任何实时任务
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// 获取任务的实时参数及错过截止期限的次数
+    pub fn get_attr(&self, task_id: usize) -> Option<(DeadlineParams, usize)> {
+        self.entries.get(&task_id).map(|e| (e.params, e.misses))
+    }
+    /// 准入控制，通过后正在运行的task_id成为实时任务并立即开始第一个作业；
+    /// 参数来自用户，换算过程中溢出同样拒绝
+    pub fn admit(&mut self, task_id: usize, params: DeadlineParams) -> bool {
+        if params.runtime == 0
+            || params.period == 0
+            || params.runtime > params.deadline
+            || params.deadline > params.period
+        {
+            return false;
+        }
+        let Some(util) = params
+            .runtime
+            .checked_mul(UTIL_SCALE)
+            .map(|scaled| scaled / params.period)
+        else {
+            return false;
+        };
+        let old_util = self.entries.get(&task_id).map_or(0, |e| e.util);
+        if self.total_util - old_util + util > UTIL_SCALE {
+            return false;
+        }
+        let now = get_time();
+        let (Some(runtime), Some(deadline), Some(period)) = (
+            ms_to_cycles(params.runtime),
+            ms_to_cycles(params.deadline),
+            ms_to_cycles(params.period),
+        ) else {
+            return false;
+        };
+        let (Some(abs_deadline), Some(next_period)) =
+            (now.checked_add(deadline), now.checked_add(period))
+        else {
+            return false;
+        };
+        self.remove(task_id);
+        self.total_util += util;
+        self.entries.insert(
+            task_id,
+            DlEntry {
+                params,
+                runtime,
+                deadline,
+                period,
+                util,
+                abs_deadline,
+                remaining: runtime,
+                next_period,
+                throttled: false,
+                job_done: false,
+                missed: false,
+                preempted: false,
+                misses: 0,
+            },
+        );
+        self.running.insert(task_id, now);
+        true
+    }
+    /// 移除一个实时任务（例如任务退出），释放其利用率，返回其错过截止期限的次数
+    pub fn remove(&mut self, task_id: usize) -> Option<usize> {
+        let entry = self.entries.remove(&task_id)?;
+        self.ready.remove(&(entry.abs_deadline, task_id));
+        self.total_util -= entry.util;
+        self.running.remove(&task_id);
+        Some(entry.misses)
+    }
+    /// 统计错过的截止期限，并为到达新周期的被节流任务释放新的作业
+    fn replenish(&mut self) {
+        let now = get_time();
+        for (&task_id, entry) in self.entries.iter_mut() {
+            entry.check_miss(now);
+            if entry.throttled && now >= entry.next_period {
+                // 落后超过一个周期时以当前时间为新周期的起点
+                let release = if now >= entry.next_period + entry.period {
+                    now
+                } else {
+                    entry.next_period
+                };
+                entry.abs_deadline = release + entry.deadline;
+                entry.next_period = release + entry.period;
+                entry.remaining = entry.runtime;
+                entry.throttled = false;
+                entry.job_done = false;
+                entry.missed = false;
+                self.ready.insert((entry.abs_deadline, task_id));
+            }
+        }
+    }
+    /// 把正在运行的实时任务task_id自上次记账以来的运行时间从预算中扣除
+    fn charge_running(&mut self, task_id: usize, now: usize) {
+        if let Some(start) = self.running.get_mut(&task_id) {
+            if let Some(entry) = self.entries.get_mut(&task_id) {
+                entry.remaining = entry.remaining.saturating_sub(now - *start);
+            }
+            *start = now;
+        }
+    }
+    /// 实时任务重新变为可运行：被抢占的作业回到就绪集合，主动让出表示作业完成
+    pub fn add(&mut self, task_id: usize) {
+        let now = get_time();
+        self.charge_running(task_id, now);
+        self.running.remove(&task_id);
+        if let Some(entry) = self.entries.get_mut(&task_id) {
+            if entry.preempted {
+                entry.preempted = false;
+                if !entry.throttled {
+                    self.ready.insert((entry.abs_deadline, task_id));
+                }
+            } else {
+                entry.job_done = true;
+                entry.throttled = true;
+            }
+        }
+    }
+    /// 取出绝对截止期限最早的就绪作业
+    pub fn fetch(&mut self) -> Option<usize> {
+        self.replenish();
+        let (_, task_id) = self.ready.pop_first()?;
+        self.running.insert(task_id, get_time());
+        Some(task_id)
+    }
+    /// 时钟中断时调用，task_id为当前正在运行的任务（可能是普通任务），返回是否需要抢占它
+    pub fn on_tick(&mut self, task_id: usize) -> bool {
+        self.replenish();
+        let now = get_time();
+        if !self.contains(task_id) {
+            // 有实时作业就绪时抢占普通任务
+            return !self.ready.is_empty();
+        }
+        self.charge_running(task_id, now);
+        let earliest = self.ready.first().map(|&(deadline, _)| deadline);
+        let entry = self.entries.get_mut(&task_id).unwrap();
+        if entry.remaining == 0 {
+            // 预算用完，节流到下一个周期
+            entry.throttled = true;
+            entry.preempted = true;
+            return true;
+        }
+        if earliest.map_or(false, |deadline| deadline < entry.abs_deadline) {
+            entry.preempted = true;
+            return true;
+        }
+        false
+    }
+}
@@ -0,0 +1,36 @@
+//! 先来先服务调度：不因时钟中断抢占，任务只在让出或退出时切换
+
+use super::Scheduler;
+use alloc::collections::VecDeque;
+
+/// FIFO调度器
+pub struct FifoScheduler {
+    ready_queue: VecDeque<usize>,
+}
+
+impl FifoScheduler {
+    /// 创建一个空的FIFO调度器
+    pub fn new() -> Self {
+        Self {
+            ready_queue: VecDeque::new(),
+        }
+    }
+}
+
+impl Scheduler for FifoScheduler {
+    fn add(&mut self, task_id: usize) {
+        self.ready_queue.push_back(task_id);
+    }
+    fn fetch(&mut self) -> Option<usize> {
+        self.ready_queue.pop_front()
+    }
+    fn remove(&mut self, task_id: usize) {
+        self.ready_queue.retain(|&id| id != task_id);
+    }
+    fn nr_ready(&self) -> usize {
+        self.ready_queue.len()
+    }
+    fn on_tick(&mut self, _task_id: usize) -> bool {
+        false
+    }
+}
@@ -0,0 +1,147 @@
+//! 多级反馈队列调度
+//!
+//! 1. 任务总是从最高级队列开始运行，同级之间轮转
+//! 2. 用完本级整个时间片的任务（计算密集型）被降到下一级，下一级的时间片更长
+//! 3. 时间片用完之前主动让出或阻塞的任务（交互型）被提升一级
+//! 4. 每隔BOOST_TICKS个时钟中断把所有任务提升到最高级，避免低级任务饿死
+
+use super::Scheduler;
+use alloc::collections::{BTreeMap, VecDeque};
+
+/// 队列级数
+const LEVELS: usize = 3;
+/// 各级队列的时间片长度（时钟中断数）
+const QUANTUM: [usize; LEVELS] = [1, 2, 4];
+/// 全局提升的周期（时钟中断数）
+const BOOST_TICKS: usize = 100;
+
+/// 每个任务的调度信息
+struct MlfqEntry {
+    /// 所在队列的级别，0为最高级
+    level: usize,
+    /// 在本级已经使用的时钟中断数
+    used: usize,
+    /// 是否是被时钟中断抢占的（而不是主动让出）
+    preempted: bool,
+}
+
+/// 多级反馈队列调度器
+pub struct MlfqScheduler {
+    queues: [VecDeque<usize>; LEVELS],
+    entries: BTreeMap<usize, MlfqEntry>,
+    ticks: usize,
+}
+
+impl MlfqScheduler {
+    /// 创建一个空的多级反馈队列调度器
+    pub fn new() -> Self {
+        Self {
+            queues: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+            entries: BTreeMap::new(),
+            ticks: 0,
+        }
+    }
+    /// 把所有任务提升到最高级
+    fn boost(&mut self) {
+        for entry in self.entries.values_mut() {
+            entry.level = 0;
+            entry.used = 0;
+        }
+        for level in 1..LEVELS {
+            while let Some(task_id) = self.queues[level].pop_front() {
+                self.queues[0].push_back(task_id);
+            }
+        }
+    }
+    /// 为正在运行的任务记一个时钟中断，返回是否需要抢占它
+    fn charge(&mut self, task_id: usize) -> bool {
+        let entry = match self.entries.get_mut(&task_id) {
+            Some(entry) => entry,
+            None => return true,
+        };
+        entry.used += 1;
+        if entry.used >= QUANTUM[entry.level] {
+            // 用完了整个时间片，降一级
+            entry.level = (entry.level + 1).min(LEVELS - 1);
+            entry.used = 0;
+            entry.preempted = true;
+            return true;
+        }
+        // 有更高级的任务就绪时同样抢占，但不改变当前任务的级别
+        let level = entry.level;
+        if self.queues[..level].iter().any(|queue| !queue.is_empty()) {
+            entry.preempted = true;
+            return true;
+        }
+        false
+    }
+}
+
+impl Scheduler for MlfqScheduler {
+    fn add(&mut self, task_id: usize) {
+        let level = match self.entries.get_mut(&task_id) {
+            Some(entry) => {
+                if entry.preempted {
+                    entry.preempted = false;
+                } else {
+                    // 时间片用完之前主动让出，提升一级
+                    entry.level = entry.level.saturating_sub(1);
+                    entry.used = 0;
+                }
+                entry.level
+            }
+            None => {
+                self.entries.insert(
+                    task_id,
+                    MlfqEntry {
+                        level: 0,
+                        used: 0,
+                        preempted: false,
+                    },
+                );
+                0
+            }
+        };
+        self.queues[level].push_back(task_id);
+    }
+    fn fetch(&mut self) -> Option<usize> {
+        self.queues.iter_mut().find_map(|queue| queue.pop_front())
+    }
+    fn remove(&mut self, task_id: usize) {
+        for queue in self.queues.iter_mut() {
+            queue.retain(|&id| id != task_id);
+        }
+        self.entries.remove(&task_id);
+    }
+    fn nr_ready(&self) -> usize {
+        self.queues.iter().map(|queue| queue.len()).sum()
+    }
+    fn on_tick(&mut self, task_id: usize) -> bool {
+        self.ticks += 1;
+        // 先结算当前任务，再做全局提升，否则刚被提升的任务会在同一个时钟中断里又被降级
+        let preempt = self.charge(task_id);
+        if self.ticks % BOOST_TICKS == 0 {
+            self.boost();
+        }
+        preempt
+    }
+}
+
+/// 多级反馈队列的测试函数：计算密集型任务降到最低级后，全局提升应当把它带回最高级
+pub fn mlfq_test() {
+    let mut scheduler = MlfqScheduler::new();
+    scheduler.add(1);
+    assert_eq!(scheduler.fetch(), Some(1));
+    // 一直占用CPU，每次被抢占后重新加入并立即被取出
+    for _ in 0..BOOST_TICKS - 1 {
+        if scheduler.on_tick(1) {
+            scheduler.add(1);
+            assert_eq!(scheduler.fetch(), Some(1));
+        }
+    }
+    assert_eq!(scheduler.entries[&1].level, LEVELS - 1);
+    // 提升发生在这个时钟中断里，结算之后任务应当位于最高级
+    scheduler.on_tick(1);
+    assert_eq!(scheduler.entries[&1].level, 0);
+    println!("mlfq_test passed!");
+}
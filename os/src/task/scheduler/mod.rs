@@ -0,0 +1,66 @@
+//! 可替换的调度策略
+//!
+//! 调度器只管理处于就绪态的任务id，任务控制块仍由[`super::TaskManager`]保存。
+//! 调度策略由编译时的环境变量`SCHED`选择（与`LOG`相同，例如`make run SCHED=mlfq`），运行时不能更改：
+//! `fifo`、`rr`（默认）、`stride`、`priority`、`mlfq`、`cfs`
+//!
+//! 实时任务由独立的EDF调度类[`DeadlineClass`]管理，总是优先于上述调度器。
+
+mod cfs;
+mod edf;
+mod fifo;
+mod mlfq;
+mod priority;
+mod rr;
+mod stride;
+
+use crate::config::CLOCK_FREQ;
+use crate::timer::TICKS_PER_SEC;
+use alloc::boxed::Box;
+
+pub use self::cfs::CfsScheduler;
+pub use self::edf::{DeadlineClass, DeadlineParams};
+pub use self::fifo::FifoScheduler;
+pub use self::mlfq::{mlfq_test, MlfqScheduler};
+pub use self::priority::PriorityScheduler;
+pub use self::rr::RoundRobinScheduler;
+pub use self::stride::StrideScheduler;
+
+/// 任务的默认优先级
+pub const DEFAULT_PRIORITY: usize = 16;
+
+/// 调度器接口
+pub trait Scheduler: Send {
+    /// 将一个就绪任务加入调度器
+    fn add(&mut self, task_id: usize);
+    /// 取出下一个要运行的任务
+    fn fetch(&mut self) -> Option<usize>;
+    /// 从调度器中移除一个任务（无论其是否就绪）
+    fn remove(&mut self, task_id: usize);
+    /// 任务离开CPU（让出、被抢占、阻塞或退出）时调用，按需结算它这一次运行的时间
+    fn put_prev(&mut self, _task_id: usize) {}
+    /// 调度器中就绪任务的个数（不含正在运行的任务）
+    fn nr_ready(&self) -> usize;
+    /// 时钟中断时调用，task_id为当前正在运行的任务，返回是否需要抢占它
+    fn on_tick(&mut self, task_id: usize) -> bool;
+    /// 设置任务的优先级，不使用优先级的策略直接忽略
+    fn set_priority(&mut self, _task_id: usize, _priority: usize) {}
+    /// 设置任务的nice值（[-20, 19]），不使用nice值的策略直接忽略
+    fn set_nice(&mut self, _task_id: usize, _nice: isize) {}
+    /// 下一个时间片的长度（时钟周期数），默认为固定的时钟中断间隔
+    fn time_slice(&self) -> usize {
+        CLOCK_FREQ / TICKS_PER_SEC
+    }
+}
+
+/// 根据编译时的环境变量`SCHED`创建调度器
+pub fn new_scheduler() -> Box<dyn Scheduler> {
+    match option_env!("SCHED") {
+        Some("fifo") => Box::new(FifoScheduler::new()),
+        Some("stride") => Box::new(StrideScheduler::new()),
+        Some("priority") => Box::new(PriorityScheduler::new()),
+        Some("mlfq") => Box::new(MlfqScheduler::new()),
+        Some("cfs") => Box::new(CfsScheduler::new()),
+        _ => Box::new(RoundRobinScheduler::new()),
+    }
+}
@@ -0,0 +1,72 @@
+//! 静态优先级调度：总是运行优先级最高的就绪任务，同优先级之间轮转
+
+use super::{Scheduler, DEFAULT_PRIORITY};
+use alloc::collections::{BTreeMap, VecDeque};
+
+/// 静态优先级调度器
+pub struct PriorityScheduler {
+    /// 优先级 -> 该优先级的就绪队列
+    queues: BTreeMap<usize, VecDeque<usize>>,
+    /// 任务id -> 优先级
+    priorities: BTreeMap<usize, usize>,
+}
+
+impl PriorityScheduler {
+    /// 创建一个空的静态优先级调度器
+    pub fn new() -> Self {
+        Self {
+            queues: BTreeMap::new(),
+            priorities: BTreeMap::new(),
+        }
+    }
+    fn priority_of(&self, task_id: usize) -> usize {
+        *self.priorities.get(&task_id).unwrap_or(&DEFAULT_PRIORITY)
+    }
+}
+
+impl Scheduler for PriorityScheduler {
+    fn add(&mut self, task_id: usize) {
+        let priority = self.priority_of(task_id);
+        self.priorities.insert(task_id, priority);
+        self.queues.entry(priority).or_default().push_back(task_id);
+    }
+    fn fetch(&mut self) -> Option<usize> {
+        let (&priority, queue) = self.queues.iter_mut().next_back()?;
+        let task_id = queue.pop_front();
+        if queue.is_empty() {
+            self.queues.remove(&priority);
+        }
+        task_id
+    }
+    fn remove(&mut self, task_id: usize) {
+        let priority = self.priority_of(task_id);
+        if let Some(queue) = self.queues.get_mut(&priority) {
+            queue.retain(|&id| id != task_id);
+            if queue.is_empty() {
+                self.queues.remove(&priority);
+            }
+        }
+        self.priorities.remove(&task_id);
+    }
+    fn nr_ready(&self) -> usize {
+        self.queues.values().map(|queue| queue.len()).sum()
+    }
+    fn on_tick(&mut self, task_id: usize) -> bool {
+        // 只有存在不低于当前任务优先级的就绪任务时才抢占
+        let priority = self.priority_of(task_id);
+        self.queues.keys().next_back().map_or(false, |&p| p >= priority)
+    }
+    fn set_priority(&mut self, task_id: usize, priority: usize) {
+        let old = self.priority_of(task_id);
+        if let Some(queue) = self.queues.get_mut(&old) {
+            if let Some(pos) = queue.iter().position(|&id| id == task_id) {
+                queue.remove(pos);
+                if queue.is_empty() {
+                    self.queues.remove(&old);
+                }
+                self.queues.entry(priority).or_default().push_back(task_id);
+            }
+        }
+        self.priorities.insert(task_id, priority);
+    }
+}
@@ -0,0 +1,36 @@
+//! 时间片轮转调度：每个时钟中断（一个时间片）都抢占当前任务
+
+use super::Scheduler;
+use alloc::collections::VecDeque;
+
+/// 时间片轮转调度器
+pub struct RoundRobinScheduler {
+    ready_queue: VecDeque<usize>,
+}
+
+impl RoundRobinScheduler {
+    /// 创建一个空的轮转调度器
+    pub fn new() -> Self {
+        Self {
+            ready_queue: VecDeque::new(),
+        }
+    }
+}
+
+impl Scheduler for RoundRobinScheduler {
+    fn add(&mut self, task_id: usize) {
+        self.ready_queue.push_back(task_id);
+    }
+    fn fetch(&mut self) -> Option<usize> {
+        self.ready_queue.pop_front()
+    }
+    fn remove(&mut self, task_id: usize) {
+        self.ready_queue.retain(|&id| id != task_id);
+    }
+    fn nr_ready(&self) -> usize {
+        self.ready_queue.len()
+    }
+    fn on_tick(&mut self, _task_id: usize) -> bool {
+        true
+    }
+}
@@ -0,0 +1,70 @@
+//! Stride调度：每次选择pass最小的任务运行，运行后pass增加BIG_STRIDE / priority
+//!
+//! 长期来看每个任务获得的CPU时间与其优先级成正比
+
+use super::{Scheduler, DEFAULT_PRIORITY};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+/// 计算步长时使用的大常数
+const BIG_STRIDE: u64 = 0x10_0000;
+
+/// 每个任务的调度信息
+struct StrideEntry {
+    pass: u64,
+    priority: usize,
+}
+
+/// Stride调度器
+pub struct StrideScheduler {
+    ready: Vec<usize>,
+    entries: BTreeMap<usize, StrideEntry>,
+}
+
+impl StrideScheduler {
+    /// 创建一个空的Stride调度器
+    pub fn new() -> Self {
+        Self {
+            ready: Vec::new(),
+            entries: BTreeMap::new(),
+        }
+    }
+}
+
+impl Scheduler for StrideScheduler {
+    fn add(&mut self, task_id: usize) {
+        // 新任务从当前最小的pass开始，避免长期占用CPU
+        let min_pass = self.entries.values().map(|e| e.pass).min().unwrap_or(0);
+        self.entries.entry(task_id).or_insert(StrideEntry {
+            pass: min_pass,
+            priority: DEFAULT_PRIORITY,
+        });
+        self.ready.push(task_id);
+    }
+    fn fetch(&mut self) -> Option<usize> {
+        let (idx, &task_id) = self
+            .ready
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, id)| self.entries[id].pass)?;
+        self.ready.swap_remove(idx);
+        let entry = self.entries.get_mut(&task_id).unwrap();
+        entry.pass += BIG_STRIDE / entry.priority as u64;
+        Some(task_id)
+    }
+    fn remove(&mut self, task_id: usize) {
+        self.ready.retain(|&id| id != task_id);
+        self.entries.remove(&task_id);
+    }
+    fn nr_ready(&self) -> usize {
+        self.ready.len()
+    }
+    fn on_tick(&mut self, _task_id: usize) -> bool {
+        true
+    }
+    fn set_priority(&mut self, task_id: usize, priority: usize) {
+        if let Some(entry) = self.entries.get_mut(&task_id) {
+            entry.priority = priority;
+        }
+    }
+}
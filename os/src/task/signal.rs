@@ -0,0 +1,124 @@
+//! 信号
+//!
+//! 每个线程有自己的待处理信号集和屏蔽信号集，信号处理动作由进程内的所有线程共享。
+//! 任务返回用户态前检查未被屏蔽的待处理信号：默认动作结束进程，退出码为信号编号的相反数；
+//! 用户处理函数执行前保存Trap上下文，处理函数返回到用户库提供的restorer，由它调用sigreturn恢复。
+
+use crate::trap::TrapContext;
+use bitflags::*;
+
+/// 最大的信号编号
+pub const MAX_SIG: usize = 31;
+
+/// 默认处理动作
+pub const SIG_DFL: usize = 0;
+/// 忽略该信号
+pub const SIG_IGN: usize = 1;
+
+/// sigprocmask的how参数：屏蔽set中的信号
+pub const SIG_BLOCK: usize = 0;
+/// sigprocmask的how参数：解除屏蔽set中的信号
+pub const SIG_UNBLOCK: usize = 1;
+/// sigprocmask的how参数：把屏蔽信号集设为set
+pub const SIG_SETMASK: usize = 2;
+
+bitflags! {
+    /// 信号集，第signum位对应编号为signum的信号
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct SignalFlags: u32 {
+        /// 终端中断（Ctrl-C）
+        const SIGINT = 1 << 2;
+        /// 非法指令
+        const SIGILL = 1 << 4;
+        /// 异常终止
+        const SIGABRT = 1 << 6;
+        /// 强制结束，不能被捕获、忽略或屏蔽
+        const SIGKILL = 1 << 9;
+        /// 用户自定义信号1
+        const SIGUSR1 = 1 << 10;
+        /// 非法内存访问
+        const SIGSEGV = 1 << 11;
+        /// 用户自定义信号2
+        const SIGUSR2 = 1 << 12;
+        /// 请求结束
+        const SIGTERM = 1 << 15;
+        /// 子进程状态改变，默认忽略
+        const SIGCHLD = 1 << 17;
+    }
+}
+
+impl SignalFlags {
+    /// 编号为signum的信号，编号无效时返回None
+    pub fn from_signum(signum: usize) -> Option<Self> {
+        if signum == 0 || signum > MAX_SIG {
+            return None;
+        }
+        Some(Self::from_bits_retain(1 << signum))
+    }
+    /// 只能执行默认动作的信号
+    pub fn uncatchable() -> Self {
+        Self::SIGKILL
+    }
+    /// 同步产生的异常信号，被屏蔽或正在处理其他信号时无法返回故障指令，强制执行默认动作
+    pub fn synchronous() -> Self {
+        Self::SIGSEGV | Self::SIGILL
+    }
+    /// 默认动作为忽略的信号
+    pub fn ignored_by_default() -> Self {
+        Self::SIGCHLD
+    }
+    /// 编号最小的信号
+    pub fn lowest_signum(&self) -> Option<usize> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.bits().trailing_zeros() as usize)
+        }
+    }
+}
+
+/// 信号处理动作，与用户库中的布局一致
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SignalAction {
+    /// 处理函数地址，或SIG_DFL、SIG_IGN
+    pub handler: usize,
+    /// 处理函数执行期间额外屏蔽的信号
+    pub mask: SignalFlags,
+    /// 处理函数的返回地址，指向用户库中调用sigreturn的代码
+    pub restorer: usize,
+}
+
+impl Default for SignalAction {
+    fn default() -> Self {
+        Self {
+            handler: SIG_DFL,
+            mask: SignalFlags::empty(),
+            restorer: 0,
+        }
+    }
+}
+
+/// 进程的信号处理动作表，下标为信号编号
+pub struct SignalActions {
+    /// 各信号的处理动作
+    pub table: [SignalAction; MAX_SIG + 1],
+}
+
+impl SignalActions {
+    /// 所有信号都执行默认动作
+    pub fn new() -> Self {
+        Self {
+            table: [SignalAction::default(); MAX_SIG + 1],
+        }
+    }
+}
+
+/// 进入用户处理函数前保存的现场，sigreturn时恢复
+#[derive(Clone, Copy)]
+pub struct SignalFrame {
+    /// 被信号打断时的Trap上下文
+    pub trap_cx: TrapContext,
+    /// 进入处理函数前的屏蔽信号集
+    pub mask: SignalFlags,
+}
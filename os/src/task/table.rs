@@ -0,0 +1,119 @@
+//! 任务表
+//!
+//! 任务id同时决定任务内核栈的位置，任务被回收后id留给之后创建的任务复用，
+//! 内核栈区域因此不会随着创建的线程数无限增长。
+//! 等待队列、定时器等可能在任务结束后才使用任务id的地方保存[`TaskHandle`]，
+//! id被复用后旧的句柄失效，不会误唤醒新的任务。
+
+use super::{TaskControlBlock, TaskStatus};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ops::{Index, IndexMut};
+
+/// 任务句柄：任务id及其所在槽位被占用的代数
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct TaskHandle {
+    /// 任务id
+    pub id: usize,
+    generation: usize,
+}
+
+/// 任务表中的一个槽位
+struct Slot {
+    task: Option<Box<TaskControlBlock>>,
+    /// 每放入一个新任务加一
+    generation: usize,
+}
+
+/// 任务表，下标为任务id
+pub struct TaskTable {
+    slots: Vec<Slot>,
+    /// 已回收、可以复用的任务id
+    free_ids: Vec<usize>,
+}
+
+impl TaskTable {
+    /// 创建一个空的任务表
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free_ids: Vec::new(),
+        }
+    }
+    /// 分配一个任务id，优先复用已回收的id，需随后调用[`Self::insert`]放入任务
+    pub fn alloc_id(&mut self) -> usize {
+        self.free_ids.pop().unwrap_or_else(|| {
+            self.slots.push(Slot {
+                task: None,
+                generation: 0,
+            });
+            self.slots.len() - 1
+        })
+    }
+    /// 把任务放入alloc_id分配的槽位
+    pub fn insert(&mut self, task_id: usize, task: Box<TaskControlBlock>) {
+        let slot = &mut self.slots[task_id];
+        assert!(slot.task.is_none(), "task id {} is in use", task_id);
+        slot.task = Some(task);
+        slot.generation += 1;
+    }
+    /// 从任务表中移除任务并回收其id，任务不能仍在某个hart上运行
+    pub fn remove(&mut self, task_id: usize) -> Option<Box<TaskControlBlock>> {
+        let task = self.slots.get_mut(task_id)?.task.take()?;
+        assert!(!task.on_cpu, "task {} is still running", task_id);
+        self.free_ids.push(task_id);
+        Some(task)
+    }
+    /// 获取任务id对应的任务
+    pub fn get(&self, task_id: usize) -> Option<&TaskControlBlock> {
+        self.slots.get(task_id)?.task.as_deref()
+    }
+    /// 获取任务id对应的任务的可变引用
+    pub fn get_mut(&mut self, task_id: usize) -> Option<&mut TaskControlBlock> {
+        self.slots.get_mut(task_id)?.task.as_deref_mut()
+    }
+    /// 获取任务当前的句柄
+    pub fn handle(&self, task_id: usize) -> TaskHandle {
+        TaskHandle {
+            id: task_id,
+            generation: self.slots[task_id].generation,
+        }
+    }
+    /// 获取句柄对应的任务，id已被回收或复用时返回None
+    pub fn resolve(&mut self, handle: TaskHandle) -> Option<&mut TaskControlBlock> {
+        let slot = self.slots.get_mut(handle.id)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        slot.task.as_deref_mut()
+    }
+    /// 句柄对应的任务是否存在且还没有退出
+    pub fn is_alive(&self, handle: TaskHandle) -> bool {
+        self.slots.get(handle.id).map_or(false, |slot| {
+            slot.generation == handle.generation
+                && slot
+                    .task
+                    .as_ref()
+                    .map_or(false, |task| task.task_status != TaskStatus::Exited)
+        })
+    }
+    /// 遍历表中的所有任务
+    pub fn iter(&self) -> impl Iterator<Item = &TaskControlBlock> {
+        self.slots.iter().filter_map(|slot| slot.task.as_deref())
+    }
+}
+
+impl Index<usize> for TaskTable {
+    type Output = TaskControlBlock;
+    fn index(&self, task_id: usize) -> &TaskControlBlock {
+        self.get(task_id)
+            .unwrap_or_else(|| panic!("task {} does not exist", task_id))
+    }
+}
+
+impl IndexMut<usize> for TaskTable {
+    fn index_mut(&mut self, task_id: usize) -> &mut TaskControlBlock {
+        self.get_mut(task_id)
+            .unwrap_or_else(|| panic!("task {} does not exist", task_id))
+    }
+}
@@ -1,26 +1,53 @@
 //! 任务管理模块
 
-use super::TaskContext;
+use super::signal::{SignalFlags, SignalFrame};
+use super::{ProcessControlBlock, TaskContext};
 use crate::config::{kernel_stack_position, TRAP_CONTEXT};
-use crate::mm::{MapPermission, MemorySet, PhysPageNum, VirtAddr, KERNEL_SPACE};
+use crate::mm::{
+    translated_refmut, MapPermission, MemorySet, PhysPageNum, VirtAddr, AT_NULL, KERNEL_SPACE,
+};
 use crate::trap::{trap_handler, TrapContext};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
 
-/// 任务控制块
+/// 任务控制块，每个线程对应一个任务
 pub struct TaskControlBlock {
+    /// 所属的进程
+    pub process: Arc<ProcessControlBlock>,
+    /// 内核栈，任务控制块释放时解除映射
+    pub kernel_stack: KernelStack,
+    /// 进程内的线程id，主线程为0
+    pub tid: usize,
     /// 任务状态
     pub task_status: TaskStatus,
     /// 任务上下文
     pub task_cx: TaskContext,
-    /// 用户地址空间
-    pub memory_set: MemorySet,
     /// Trap上下文物理页号
     pub trap_cx_ppn: PhysPageNum,
-    /// 应用数据大小，从0x0到用户栈结束地址的大小
-    pub base_size: usize,
-    /// 堆底地址
-    pub heap_bottom: usize,
-    /// 
-    pub program_brk: usize,
+    /// nice值，范围为[-20, 19]，越小获得的CPU份额越大
+    pub nice: isize,
+    /// 用户态累计运行时间（时钟周期数）
+    pub user_time: usize,
+    /// 内核态累计运行时间（时钟周期数）
+    pub kernel_time: usize,
+    /// 上一次记账的时间点，用于计算下一段用户态或内核态时间
+    pub time_stamp: usize,
+    /// 主动让出CPU的次数
+    pub voluntary_switches: usize,
+    /// 被抢占的次数
+    pub involuntary_switches: usize,
+    /// 退出码，任务退出后设置
+    pub exit_code: Option<i32>,
+    /// 任务是否仍占用某个hart，换下的任务在切回空闲循环前上下文尚未保存完毕
+    pub on_cpu: bool,
+    /// 任务在阻塞之前就被其他hart唤醒，下一次阻塞直接返回
+    pub wakeup_pending: bool,
+    /// 待处理的信号
+    pub signals: SignalFlags,
+    /// 被屏蔽的信号，待处理但被屏蔽的信号在解除屏蔽后才会递送
+    pub signal_mask: SignalFlags,
+    /// 正在执行用户信号处理函数时保存的现场，同一时刻只处理一个信号
+    pub signal_frame: Option<SignalFrame>,
 }
 
 impl TaskControlBlock {
@@ -30,76 +57,172 @@ impl TaskControlBlock {
     }
     /// 获取当前正在运行的应用程序地址空间的token
     pub fn get_user_token(&self) -> usize {
-        self.memory_set.token()
+        self.process.inner_exclusive_access().get_user_token()
     }
-    /// 创建一个新的任务控制块
-    pub fn new(elf_data: &[u8], app_id: usize) -> Self {
+    /// 为应用创建进程及其主线程，task_id同时作为进程id，args为传给应用的命令行参数
+    pub fn new(elf_data: &[u8], task_id: usize, args: &[&str]) -> Self {
         // 根据传入的elf数据构造应用的地址空间，包括跳板页、Trap上下文页、用户栈
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
+        let (memory_set, user_sp, heap_bottom, entry_point, auxv) = MemorySet::from_elf(elf_data);
+        // 按System V约定在用户栈上构造argc、argv、envp和auxv
+        let (user_sp, argv_base) = init_user_stack(memory_set.token(), user_sp, args, &auxv);
         // 通过多级页表找到应用地址空间中的Trap上下文实际的物理页号
         let trap_cx_ppn = memory_set
             .translate(VirtAddr::from(TRAP_CONTEXT).into())
             .unwrap()
             .ppn();
-        let task_status = TaskStatus::Ready;
-        // 获取应用的内核栈预计在内核空间中的位置
-        let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(app_id);
-        // 在内核地址空间中插入该逻辑段
-        KERNEL_SPACE.exclusive_access().insert_framed_area(
-            kernel_stack_bottom.into(),
-            kernel_stack_top.into(),
-            MapPermission::R | MapPermission::W,
-        );
-        let task_control_block = Self {
-            task_status,
-            task_cx: TaskContext::goto_trap_return(kernel_stack_top),
-            memory_set,
-            trap_cx_ppn,
-            base_size: user_sp,
-            heap_bottom: user_sp,
-            program_brk: user_sp,
-        };
+        let process = Arc::new(ProcessControlBlock::new(task_id, memory_set, user_sp, heap_bottom));
+        let tid = process.inner_exclusive_access().alloc_tid(task_id);
+        let task_control_block = Self::new_task(process, tid, task_id, trap_cx_ppn);
         // 获取指向当前应用TrapContext的可变引用
         let trap_cx = task_control_block.get_trap_cx();
         *trap_cx = TrapContext::app_init_context(
             entry_point,
             user_sp,
-            KERNEL_SPACE.exclusive_access().token(),
-            kernel_stack_top,
+            KERNEL_SPACE.lock().token(),
+            task_control_block.kernel_stack.top(),
             trap_handler as usize,
         );
+        // 同时通过a0、a1传递argc和argv
+        trap_cx.x[10] = args.len();
+        trap_cx.x[11] = argv_base;
         task_control_block
     }
-    /// change the location of the program break. return None if failed.
-    pub fn change_program_brk(&mut self, size: i32) -> Option<usize> {
-        let old_break = self.program_brk;
-        let new_brk = self.program_brk as isize + size as isize;
-        if new_brk < self.heap_bottom as isize {
-            return None;
+    /// 在process中创建一个从entry开始执行的新线程，arg通过a0传递
+    pub fn new_thread(process: &Arc<ProcessControlBlock>, task_id: usize, entry: usize, arg: usize) -> Self {
+        let mut process_inner = process.inner_exclusive_access();
+        let tid = process_inner.alloc_tid(task_id);
+        let (user_sp, trap_cx_ppn) = process_inner.alloc_thread_resources(tid);
+        drop(process_inner);
+        let task_control_block = Self::new_task(process.clone(), tid, task_id, trap_cx_ppn);
+        let trap_cx = task_control_block.get_trap_cx();
+        *trap_cx = TrapContext::app_init_context(
+            entry,
+            user_sp,
+            KERNEL_SPACE.lock().token(),
+            task_control_block.kernel_stack.top(),
+            trap_handler as usize,
+        );
+        trap_cx.x[10] = arg;
+        task_control_block
+    }
+    /// 分配内核栈并构造处于就绪态的任务控制块，内核栈的位置由task_id决定
+    fn new_task(
+        process: Arc<ProcessControlBlock>,
+        tid: usize,
+        task_id: usize,
+        trap_cx_ppn: PhysPageNum,
+    ) -> Self {
+        let kernel_stack = KernelStack::new(task_id);
+        let kernel_stack_top = kernel_stack.top();
+        Self {
+            process,
+            kernel_stack,
+            tid,
+            task_status: TaskStatus::Ready,
+            task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+            trap_cx_ppn,
+            nice: 0,
+            user_time: 0,
+            kernel_time: 0,
+            time_stamp: 0,
+            voluntary_switches: 0,
+            involuntary_switches: 0,
+            exit_code: None,
+            on_cpu: false,
+            wakeup_pending: false,
+            signals: SignalFlags::empty(),
+            signal_mask: SignalFlags::empty(),
+            signal_frame: None,
         }
-        let result = if size < 0 {
-            self.memory_set
-                .shrink_to(VirtAddr(self.heap_bottom), VirtAddr(new_brk as usize))
-        } else {
-            self.memory_set
-                .append_to(VirtAddr(self.heap_bottom), VirtAddr(new_brk as usize))
-        };
-        if result {
-            self.program_brk = new_brk as usize;
-            Some(old_break)
-        } else {
-            None
+    }
+}
+
+/// 任务的内核栈，位于内核地址空间中由任务id决定的位置
+pub struct KernelStack {
+    task_id: usize,
+}
+
+impl KernelStack {
+    /// 在内核地址空间中映射任务task_id的内核栈
+    pub fn new(task_id: usize) -> Self {
+        let (kernel_stack_bottom, kernel_stack_top) = kernel_stack_position(task_id);
+        KERNEL_SPACE.lock().insert_framed_area(
+            kernel_stack_bottom.into(),
+            kernel_stack_top.into(),
+            MapPermission::R | MapPermission::W,
+        );
+        Self { task_id }
+    }
+    /// 内核栈的栈顶地址
+    pub fn top(&self) -> usize {
+        kernel_stack_position(self.task_id).1
+    }
+}
+
+impl Drop for KernelStack {
+    fn drop(&mut self) {
+        // 任务id随后可能被复用，新任务会在同一位置重新映射内核栈
+        let (kernel_stack_bottom, _) = kernel_stack_position(self.task_id);
+        KERNEL_SPACE
+            .lock()
+            .remove_area_with_start_vpn(VirtAddr::from(kernel_stack_bottom).into());
+    }
+}
+
+/// 在用户栈顶构造System V风格的初始栈，返回新的栈顶和argv数组的地址
+///
+/// 栈布局（从高地址到低地址）：
+/// argv字符串 | 对齐 | auxv（以AT_NULL结束）| envp（空，仅NULL）| argv指针数组（以NULL结束）| argc <- sp
+fn init_user_stack(
+    token: usize,
+    mut user_sp: usize,
+    args: &[&str],
+    auxv: &[(usize, usize)],
+) -> (usize, usize) {
+    // 先拷贝参数字符串，记录它们在用户空间中的地址
+    let mut argv: Vec<usize> = Vec::new();
+    for arg in args {
+        user_sp -= arg.len() + 1;
+        let mut p = user_sp;
+        for c in arg.as_bytes() {
+            *translated_refmut(token, p as *mut u8) = *c;
+            p += 1;
         }
+        *translated_refmut(token, p as *mut u8) = 0;
+        argv.push(user_sp);
+    }
+    // argc + argv + NULL + envp的NULL + auxv + AT_NULL
+    let words = 1 + argv.len() + 1 + 1 + 2 * (auxv.len() + 1);
+    user_sp = (user_sp - words * core::mem::size_of::<usize>()) & !0xf;
+    let mut p = user_sp;
+    let mut push = |value: usize| {
+        *translated_refmut(token, p as *mut usize) = value;
+        p += core::mem::size_of::<usize>();
+    };
+    push(argv.len());
+    for ptr in argv.iter() {
+        push(*ptr);
+    }
+    push(0);
+    push(0);
+    for (key, value) in auxv.iter() {
+        push(*key);
+        push(*value);
     }
+    push(AT_NULL);
+    push(0);
+    (user_sp, user_sp + core::mem::size_of::<usize>())
 }
 
 #[derive(Copy, Clone, PartialEq)]
-/// task status:Ready, Running, Exited
+/// task status:Ready, Running, Blocked, Exited
 pub enum TaskStatus {
     /// 就绪态
     Ready,
     /// 运行态
     Running,
+    /// 阻塞态，等待被唤醒
+    Blocked,
     /// 退出态
     Exited,
 }
@@ -1,8 +1,16 @@
 //! m模式时钟寄存器操作接口
 
 use riscv::register::time;
-use crate::config::CLOCK_FREQ;
+use crate::config::{CLOCK_FREQ, RTC_BASE};
 use crate::sbi::set_timer;
+use crate::sync::SpinNoIrqLock;
+#[cfg(feature = "tickless")]
+use crate::task::need_time_slice;
+use crate::task::{current_time_slice, wakeup_task, TaskHandle};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use lazy_static::*;
 
 /// 获取当前时间,读取mtime寄存器
 pub fn get_time() -> usize {
@@ -10,19 +18,226 @@ pub fn get_time() -> usize {
 }
 
 const MSEC_PER_SEC: usize = 1000;
+const NSEC_PER_SEC: usize = 1_000_000_000;
 
-/// 获取当前时间到毫秒数
-pub fn get_time_ms() -> usize {
-    time::read() / (CLOCK_FREQ / MSEC_PER_SEC)
+/// goldfish RTC寄存器：读TIME_LOW时锁存高32位到TIME_HIGH
+const RTC_TIME_LOW: usize = 0x00;
+const RTC_TIME_HIGH: usize = 0x04;
+
+/// 读取goldfish RTC，得到自1970年以来的纳秒数
+fn read_rtc_ns() -> usize {
+    unsafe {
+        let low = ((RTC_BASE + RTC_TIME_LOW) as *const u32).read_volatile() as usize;
+        let high = ((RTC_BASE + RTC_TIME_HIGH) as *const u32).read_volatile() as usize;
+        high << 32 | low
+    }
+}
+
+/// 启动时的墙上时间（纳秒）及对应的time寄存器值，之后的墙上时间由time寄存器推算
+static BOOT_RTC_NS: AtomicUsize = AtomicUsize::new(0);
+static BOOT_CYCLES: AtomicUsize = AtomicUsize::new(0);
+
+/// 从RTC读取一次墙上时间作为实时时钟的起点，需在RTC所在的MMIO区域映射后调用
+pub fn init() {
+    BOOT_CYCLES.store(get_time(), Relaxed);
+    BOOT_RTC_NS.store(read_rtc_ns(), Relaxed);
+    println!("[kernel] RTC: {}s since epoch", BOOT_RTC_NS.load(Relaxed) / NSEC_PER_SEC);
+}
+
+/// 单调时钟：开机以来经过的时间
+pub fn monotonic_time() -> TimeSpec {
+    TimeSpec::from_cycles(get_time())
+}
+
+/// 实时时钟：自1970年以来经过的时间
+pub fn realtime() -> TimeSpec {
+    let elapsed = TimeSpec::from_cycles(get_time() - BOOT_CYCLES.load(Relaxed));
+    let ns = BOOT_RTC_NS.load(Relaxed) + elapsed.sec * NSEC_PER_SEC + elapsed.nsec;
+    TimeSpec {
+        sec: ns / NSEC_PER_SEC,
+        nsec: ns % NSEC_PER_SEC,
+    }
+}
+
+/// 把时钟周期数换算为毫秒数
+pub fn cycles_to_ms(cycles: usize) -> usize {
+    cycles / (CLOCK_FREQ / MSEC_PER_SEC)
+}
+
+const USEC_PER_SEC: usize = 1_000_000;
+
+/// 秒与微秒表示的时间
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct TimeVal {
+    /// 秒
+    pub sec: usize,
+    /// 微秒
+    pub usec: usize,
+}
+
+impl TimeVal {
+    /// 由时钟周期数构造
+    pub fn from_cycles(cycles: usize) -> Self {
+        Self {
+            sec: cycles / CLOCK_FREQ,
+            usec: cycles % CLOCK_FREQ * USEC_PER_SEC / CLOCK_FREQ,
+        }
+    }
+}
+
+/// 秒与纳秒表示的时间
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct TimeSpec {
+    /// 秒
+    pub sec: usize,
+    /// 纳秒
+    pub nsec: usize,
+}
+
+impl TimeSpec {
+    /// 由时钟周期数构造
+    pub fn from_cycles(cycles: usize) -> Self {
+        Self {
+            sec: cycles / CLOCK_FREQ,
+            nsec: cycles % CLOCK_FREQ * NSEC_PER_SEC / CLOCK_FREQ,
+        }
+    }
+}
+
+/// 默认的每秒时钟中断次数
+pub const TICKS_PER_SEC: usize = 100;
+
+/// 已经处理的时钟中断次数，用于比较周期模式和无滴答模式
+static TIMER_INTERRUPTS: AtomicUsize = AtomicUsize::new(0);
+
+/// 记录一次时钟中断
+pub fn count_timer_interrupt() {
+    TIMER_INTERRUPTS.fetch_add(1, Relaxed);
 }
 
-const TICKS_PER_SEC: usize = 100;
+/// 获取已经处理的时钟中断次数
+pub fn timer_interrupts() -> usize {
+    TIMER_INTERRUPTS.load(Relaxed)
+}
 
 /// 设置下一次始终中断触发时间，即mtimecmp寄存器的值
+#[cfg(not(feature = "tickless"))]
 pub fn set_next_trigger() {
     /* 时间片轮转 
      * 注意该函数并没有直接永久的设置一个时间片的大小 
      * 仅仅只是设置下一次中断的时间
-     * 因为每次在Trap_handler中match一次时钟中断时，就调用一次set_next_trigger()来设置周期性时间片*/
-    set_timer(get_time() + CLOCK_FREQ / TICKS_PER_SEC);
+     * 因为每次在Trap_handler中match一次时钟中断时，就调用一次set_next_trigger()来设置周期性时间片
+     * 时间片的长度由调度策略决定，默认为CLOCK_FREQ / TICKS_PER_SEC */
+    set_timer(get_time() + current_time_slice());
+}
+
+/// 无滴答模式下轮询控制台的频率，独占CPU的任务也能被Ctrl-C打断
+#[cfg(feature = "tickless")]
+const CONSOLE_POLLS_PER_SEC: usize = 10;
+
+/// 无滴答模式：下一次时钟中断取时间片到期和最近的定时器到期时间中较早的一个，
+/// 只有当前任务可运行时不需要时间片，此时只按较低的频率轮询控制台
+#[cfg(feature = "tickless")]
+pub fn set_next_trigger() {
+    let mut next = get_time() + CLOCK_FREQ / CONSOLE_POLLS_PER_SEC;
+    if need_time_slice() {
+        next = get_time() + current_time_slice();
+    }
+    if let Some(deadline) = next_timer_deadline() {
+        next = next.min(deadline);
+    }
+    set_timer(next);
+}
+
+/// 无滴答模式下空闲hart的时钟中断：其他hart让任务就绪时不会通知本hart，
+/// 因此最多等待一个默认时钟周期就回到空闲循环检查就绪队列
+#[cfg(feature = "tickless")]
+pub fn set_idle_trigger() {
+    let mut next = get_time() + CLOCK_FREQ / TICKS_PER_SEC;
+    if let Some(deadline) = next_timer_deadline() {
+        next = next.min(deadline);
+    }
+    set_timer(next);
+}
+
+/// 定时器回调
+type TimerCallback = Box<dyn FnOnce() + Send>;
+
+/// 定时器的句柄，用于取消定时器
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct TimerId {
+    deadline: usize,
+    seq: usize,
+}
+
+/// 内核软件定时器：按(到期时间, 序号)排序，到期时间相同的按加入顺序触发
+struct TimerQueue {
+    timers: BTreeMap<TimerId, TimerCallback>,
+    next_seq: usize,
+}
+
+lazy_static! {
+    /// 全局定时器队列，在时钟中断中访问，持锁时关中断
+    static ref TIMERS: SpinNoIrqLock<TimerQueue> = SpinNoIrqLock::new(TimerQueue {
+        timers: BTreeMap::new(),
+        next_seq: 0,
+    });
+}
+
+/// 添加一个在deadline（时钟周期）到期的定时器，到期后在时钟中断中调用callback
+pub fn add_timer(deadline: usize, callback: impl FnOnce() + Send + 'static) -> TimerId {
+    let mut queue = TIMERS.lock();
+    let id = TimerId {
+        deadline,
+        seq: queue.next_seq,
+    };
+    queue.next_seq += 1;
+    queue.timers.insert(id, Box::new(callback));
+    drop(queue);
+    // 新的定时器可能比已设置的时钟中断更早到期
+    #[cfg(feature = "tickless")]
+    set_next_trigger();
+    id
+}
+
+/// 取消一个尚未到期的定时器，返回是否取消成功
+#[allow(unused)]
+pub fn cancel_timer(id: TimerId) -> bool {
+    TIMERS.lock().timers.remove(&id).is_some()
+}
+
+/// 最近的定时器到期时间
+#[allow(unused)]
+fn next_timer_deadline() -> Option<usize> {
+    TIMERS
+        .lock()
+        .timers
+        .first_key_value()
+        .map(|(id, _)| id.deadline)
+}
+
+/// 依次触发所有到期的定时器
+pub fn check_timers() {
+    let now = get_time();
+    loop {
+        let mut queue = TIMERS.lock();
+        match queue.timers.first_key_value() {
+            Some((id, _)) if id.deadline <= now => {
+                let (_, callback) = queue.timers.pop_first().unwrap();
+                // 回调可能再次添加定时器，调用前释放借用
+                drop(queue);
+                callback();
+            }
+            _ => break,
+        }
+    }
+}
+
+/// 让任务在deadline（时钟周期）时被唤醒，任务需随后自行阻塞
+pub fn add_sleeper(deadline: usize, task: TaskHandle) -> TimerId {
+    add_timer(deadline, move || {
+        wakeup_task(task);
+    })
 }
@@ -4,6 +4,7 @@ use riscv::register::sstatus::{self, Sstatus, SPP};
 
 // repr(C)属性告诉编译器按照C语言的结构体布局来组织内存
 #[repr(C)]
+#[derive(Clone, Copy)]
 /// 定义Trap上下文结构体
 pub struct TrapContext {
     /* 需要保存的寄存器上下文
@@ -26,6 +27,8 @@ pub struct TrapContext {
     pub kernel_sp: usize,
     /// trap处理函数地址
     pub trap_handler: usize,
+    /// 返回用户态时所在hart的id，陷入内核时恢复到tp
+    pub kernel_tp: usize,
 }
 
 impl TrapContext {
@@ -50,6 +53,7 @@ impl TrapContext {
             kernel_satp,  
             kernel_sp,    
             trap_handler,
+            kernel_tp: 0,
         };
         cx.set_sp(sp); // 设置当前上下文用户栈指针
         cx
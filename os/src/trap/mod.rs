@@ -8,23 +8,36 @@
 //! 控制权转交给[`trap_handler()`]
 //! 
 //! 该函数基于scause寄存器中不同的异常，调用不同的处理函数
+//!
+//! 内核态的Trap从 `__kernel_trap` 进入，现场保存在内核栈上，
+//! 由[`kernel_trap_handler()`]处理时钟中断和外部中断，内核中的页错误打印现场后关机。
+//! 系统调用期间打开中断，长时间的系统调用可以被时钟中断抢占。
 
 mod context;
 
-use crate::config::{TRAMPOLINE, TRAP_CONTEXT};
+use crate::config::TRAMPOLINE;
+use crate::console::{deliver_ctrl_c, poll_ctrl_c};
+use crate::mm::handle_ipi;
+use crate::plic;
+use crate::sbi::shutdown;
+use crate::stack_trace::print_stack_trace_from;
+use crate::sync::preemptible;
 use crate::syscall::syscall;
 use crate::task::{
-    current_trap_cx, current_user_token, exit_current_and_run_next, suspend_current_and_run_next,
+    current_trap_cx, current_trap_cx_user_va, current_user_token, exit_if_killed, handle_signals,
+    preempt_current_and_run_next, raise_current_signal, scheduler_tick, user_time_end,
+    user_time_start, SignalFlags,
 };
-use crate::timer::set_next_trigger;
+use crate::timer::{check_timers, count_timer_interrupt, set_next_trigger};
 use core::arch::{asm, global_asm};
 use riscv::register::{
     mtvec::TrapMode,
     scause::{self, Exception, Interrupt, Trap},
-    sie, stval, stvec,
+    sie, sstatus, stval, stvec,
 };
 
 global_asm!(include_str!("trap.S"));
+global_asm!(include_str!("kernel_trap.S"));
 
 /// 初始化 CSR `stvec` 的入口地址为 `__alltraps`
 pub fn init() {
@@ -33,8 +46,11 @@ pub fn init() {
 
 /// 设置内核中断的处理函数
 fn set_kernel_trap_entry() {
+    extern "C" {
+        fn __kernel_trap();
+    }
     unsafe {
-        stvec::write(trap_from_kernel as usize, TrapMode::Direct);
+        stvec::write(__kernel_trap as usize, TrapMode::Direct);
     }
 }
 
@@ -52,32 +68,81 @@ pub fn enable_timer_interrupt() {
     }
 }
 
+/// 使能软件中断，用于接收其他hart发来的核间中断
+pub fn enable_software_interrupt() {
+    unsafe {
+        sie::set_ssoft();
+    }
+}
+
+/// 使能外部中断
+pub fn enable_external_interrupt() {
+    unsafe {
+        sie::set_sext();
+    }
+}
+
+/// 处理时钟中断，顺带轮询控制台的Ctrl-C，返回调度策略是否要求抢占当前任务
+fn handle_timer_interrupt() -> bool {
+    count_timer_interrupt();
+    poll_ctrl_c();
+    // 先触发到期的定时器（例如唤醒睡眠任务），再按新的就绪任务数设置下一次时钟中断
+    check_timers();
+    set_next_trigger();
+    scheduler_tick()
+}
+
+/// 处理外部中断：目前没有注册中断处理的设备，领取后直接完成
+fn handle_external_interrupt() {
+    while let Some(irq) = plic::claim() {
+        println!("[kernel] unhandled external interrupt {}", irq);
+        plic::complete(irq);
+    }
+}
+
 #[no_mangle]
 /// 处理中断、异常、系统调用
 pub fn trap_handler() -> ! {
     set_kernel_trap_entry();
+    user_time_end();
     let cx = current_trap_cx();
     let scause = scause::read();
     let stval = stval::read();
     match scause.cause() {
         Trap::Exception(Exception::UserEnvCall) => {
             cx.sepc += 4;
-            cx.x[10] = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]) as usize;
+            // 系统调用期间打开中断，返回用户态前再关闭
+            unsafe {
+                sstatus::set_sie();
+            }
+            let result = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]);
+            unsafe {
+                sstatus::clear_sie();
+            }
+            cx.x[10] = result as usize;
         }
         Trap::Exception(Exception::StoreFault)
         | Trap::Exception(Exception::StorePageFault)
         | Trap::Exception(Exception::LoadFault)
         | Trap::Exception(Exception::LoadPageFault) => {
-            println!("[kernel] PageFault in application, bad addr = {:#x}, bad instruction = {:#x}, kernel killed it.", stval, cx.sepc);
-            exit_current_and_run_next();
+            println!("[kernel] PageFault in application, bad addr = {:#x}, bad instruction = {:#x}, raise SIGSEGV.", stval, cx.sepc);
+            raise_current_signal(SignalFlags::SIGSEGV);
         }
         Trap::Exception(Exception::IllegalInstruction) => {
-            println!("[kernel] IllegalInstruction in application, kernel killed it.");
-            exit_current_and_run_next();
+            println!("[kernel] IllegalInstruction in application, raise SIGILL.");
+            raise_current_signal(SignalFlags::SIGILL);
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
-            set_next_trigger();
-            suspend_current_and_run_next();
+            // 由调度策略决定是否抢占当前任务
+            if handle_timer_interrupt() {
+                preempt_current_and_run_next();
+            }
+        }
+        Trap::Interrupt(Interrupt::SupervisorExternal) => {
+            handle_external_interrupt();
+        }
+        Trap::Interrupt(Interrupt::SupervisorSoft) => {
+            handle_ipi();
         }
         _ => {
             panic!(
@@ -87,6 +152,12 @@ pub fn trap_handler() -> ! {
             );
         }
     }
+    // 中断处理中收到的Ctrl-C在这里发送，之后才检查当前线程是否被结束
+    deliver_ctrl_c();
+    // 主线程在其他hart上退出时当前线程已被结束，它可能正在用户态运行，由核间中断带进内核
+    exit_if_killed();
+    // 返回用户态前处理待处理的信号
+    handle_signals();
     trap_return();
 }
 
@@ -95,8 +166,13 @@ pub fn trap_handler() -> ! {
 /// set the reg a0 = trap上下文指针, reg a1 = 用户页表物理地址
 /// 最后跳转至 __restore 返回用户态
 pub fn trap_return() -> ! {
+    // 切换到用户态的Trap入口之后不能再响应内核中的中断
+    unsafe {
+        sstatus::clear_sie();
+    }
     set_user_trap_entry();
-    let trap_cx_ptr = TRAP_CONTEXT;
+    user_time_start();
+    let trap_cx_ptr = current_trap_cx_user_va();
     let user_satp = current_user_token();
     extern "C" {
         fn __alltraps();
@@ -119,11 +195,68 @@ pub fn trap_return() -> ! {
     }
 }
 
+/// 内核态Trap的现场，布局与kernel_trap.S一致
+#[repr(C)]
+struct KernelTrapFrame {
+    /// 通用寄存器，其中tp未保存
+    x: [usize; 32],
+    sstatus: usize,
+    sepc: usize,
+}
+
 #[no_mangle]
-/// 未实现内核态处理异常、中断
-/// Todo: Chapter 9: I/O device
-pub fn trap_from_kernel() -> ! {
-    panic!("a trap from kernel!");
+/// 处理内核态的中断和异常，只有系统调用期间打开中断，因此中断总是打断了某个任务的系统调用
+fn kernel_trap_handler(frame: &mut KernelTrapFrame) {
+    let scause = scause::read();
+    let stval = stval::read();
+    match scause.cause() {
+        Trap::Interrupt(Interrupt::SupervisorTimer) => {
+            // 持有自旋锁时不能切换，本次时间片到期的抢占推迟到下一次时钟中断
+            if handle_timer_interrupt() && preemptible() {
+                preempt_current_and_run_next();
+            }
+        }
+        Trap::Interrupt(Interrupt::SupervisorExternal) => {
+            handle_external_interrupt();
+        }
+        Trap::Interrupt(Interrupt::SupervisorSoft) => {
+            handle_ipi();
+        }
+        Trap::Exception(
+            e @ (Exception::StoreFault
+            | Exception::StorePageFault
+            | Exception::LoadFault
+            | Exception::LoadPageFault
+            | Exception::InstructionFault
+            | Exception::InstructionPageFault),
+        ) => {
+            println!(
+                "[kernel] {:?} in kernel: sepc = {:#x}, stval = {:#x}, scause = {:#x}, sstatus = {:#x}",
+                e,
+                frame.sepc,
+                stval,
+                scause.bits(),
+                frame.sstatus,
+            );
+            println!(
+                "[kernel] ra = {:#x}, sp = {:#x}, fp = {:#x}",
+                frame.x[1], frame.x[2], frame.x[8]
+            );
+            // 从陷入前的栈帧开始回溯
+            unsafe {
+                print_stack_trace_from(frame.x[8]);
+            }
+            shutdown(true);
+        }
+        _ => {
+            panic!(
+                "Unsupported trap from kernel {:?}, stval = {:#x}, sepc = {:#x}!",
+                scause.cause(),
+                stval,
+                frame.sepc
+            );
+        }
+    }
 }
 
 pub use self::context::TrapContext;
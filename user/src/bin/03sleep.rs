@@ -4,15 +4,13 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::{get_time, yield_};
+use user_lib::{get_time, sleep};
 
 #[no_mangle]
 fn main() -> i32 {
     let current_timer = get_time();
-    let wait_for = current_timer + 3000;
-    while get_time() < wait_for {
-        yield_();
-    }
+    sleep(3000);
+    assert!(get_time() >= current_timer + 3000);
     println!("Test sleep OK!");
     0
 }
@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{argc, argv, getauxval, AT_ENTRY, AT_PAGESZ, AT_PHDR, AT_PHNUM};
+
+#[no_mangle]
+fn main() -> i32 {
+    println!("argc = {}, argv[0] = {}", argc(), argv(0).unwrap_or("?"));
+    if argv(0) != Some("auxv_test") {
+        println!("Test auxv failed: bad argv!");
+        return -1;
+    }
+    if getauxval(AT_PAGESZ) != Some(4096) {
+        println!("Test auxv failed: bad AT_PAGESZ!");
+        return -1;
+    }
+    // 程序被重定位到内核选定的基址，入口地址应与_start的实际地址一致
+    let entry = getauxval(AT_ENTRY).unwrap();
+    println!("loaded at entry = {:#x}, main = {:#x}", entry, main as usize);
+    if entry != user_lib::_start as usize {
+        println!("Test auxv failed: bad AT_ENTRY!");
+        return -1;
+    }
+    // 程序头表被映射到内存中，第一个程序头应能正常读取
+    let phdr = getauxval(AT_PHDR).unwrap();
+    let phnum = getauxval(AT_PHNUM).unwrap();
+    println!("phdr = {:#x}, phnum = {}", phdr, phnum);
+    if phdr == 0 || phnum == 0 {
+        println!("Test auxv failed: bad AT_PHDR!");
+        return -1;
+    }
+    println!("Test auxv OK!");
+    0
+}
@@ -0,0 +1,35 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    clock_gettime, get_time_us, sleep, TimeSpec, CLOCK_MONOTONIC, CLOCK_REALTIME, EINVAL,
+};
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut realtime = TimeSpec::default();
+    assert_eq!(clock_gettime(CLOCK_REALTIME, &mut realtime), 0);
+    // goldfish RTC给出的是宿主机时间，必然晚于2020-01-01
+    assert!(realtime.sec > 1_577_836_800);
+    println!("clock_test: realtime = {}.{:09}s", realtime.sec, realtime.nsec);
+
+    let mut start = TimeSpec::default();
+    let mut end = TimeSpec::default();
+    assert_eq!(clock_gettime(CLOCK_MONOTONIC, &mut start), 0);
+    sleep(100);
+    assert_eq!(clock_gettime(CLOCK_MONOTONIC, &mut end), 0);
+    let elapsed_ns = (end.sec - start.sec) * 1_000_000_000 + end.nsec - start.nsec;
+    assert!(elapsed_ns >= 100_000_000);
+    println!("clock_test: slept {}ns", elapsed_ns);
+
+    // 微秒精度：连续两次读取不会退回
+    let t0 = get_time_us();
+    let t1 = get_time_us();
+    assert!(t1 >= t0);
+    assert_eq!(clock_gettime(42, &mut start), EINVAL);
+    println!("clock_test passed!");
+    0
+}
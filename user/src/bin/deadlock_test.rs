@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    enable_deadlock_detect, exit, mutex_blocking_create, mutex_lock, mutex_unlock, sleep,
+    thread_create, waittid, EDEADLK,
+};
+
+static mut MUTEX_A: usize = 0;
+static mut MUTEX_B: usize = 0;
+
+/// 先锁A再锁B
+fn first(_arg: usize) -> ! {
+    unsafe {
+        assert_eq!(mutex_lock(MUTEX_A), 0);
+        sleep(20);
+        assert_eq!(mutex_lock(MUTEX_B), 0);
+        mutex_unlock(MUTEX_B);
+        mutex_unlock(MUTEX_A);
+    }
+    exit(0);
+    unreachable!();
+}
+
+/// 先锁B再锁A，此时first已经持有A并在等待B，申请A会形成环路等待
+fn second(_arg: usize) -> ! {
+    let mut code = 0;
+    unsafe {
+        assert_eq!(mutex_lock(MUTEX_B), 0);
+        sleep(40);
+        if mutex_lock(MUTEX_A) == EDEADLK {
+            code = 1;
+        } else {
+            mutex_unlock(MUTEX_A);
+        }
+        mutex_unlock(MUTEX_B);
+    }
+    exit(code);
+    unreachable!();
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(enable_deadlock_detect(true), 0);
+    unsafe {
+        MUTEX_A = mutex_blocking_create() as usize;
+        MUTEX_B = mutex_blocking_create() as usize;
+    }
+    let first_tid = thread_create(first as usize, 0);
+    let second_tid = thread_create(second as usize, 0);
+    assert_eq!(waittid(second_tid as usize), 1);
+    assert_eq!(waittid(first_tid as usize), 0);
+    println!("deadlock_test passed!");
+    0
+}
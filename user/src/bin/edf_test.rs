@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{get_time, sched_getattr, sched_setattr, yield_, SchedAttr, EINVAL};
+
+/// 作业个数
+const JOBS: usize = 10;
+
+/// 忙等待约ms毫秒，模拟一个作业的计算量
+fn busy(ms: isize) {
+    let start = get_time();
+    while get_time() - start < ms {}
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let mut attr = SchedAttr::default();
+    assert_eq!(sched_getattr(&mut attr), EINVAL);
+    // 利用率超过1，必须被准入控制拒绝
+    assert_eq!(sched_setattr(20, 10, 100), EINVAL);
+    assert_eq!(sched_setattr(120, 100, 100), EINVAL);
+    // 周期为0，或换算成时钟周期时溢出
+    assert_eq!(sched_setattr(0, 0, 0), EINVAL);
+    assert_eq!(sched_setattr(usize::MAX, usize::MAX, usize::MAX), EINVAL);
+    assert_eq!(sched_setattr(30, 100, 100), 0);
+    let start = get_time();
+    for _ in 0..JOBS {
+        busy(10);
+        // 本周期的作业完成，节流到下一个周期
+        yield_();
+    }
+    let elapsed = get_time() - start;
+    assert_eq!(sched_getattr(&mut attr), 0);
+    println!(
+        "edf_test: {} jobs in {}ms, runtime = {}ms, period = {}ms, misses = {}",
+        JOBS, elapsed, attr.runtime, attr.period, attr.misses
+    );
+    // 作业按周期释放，总时间不会少于(JOBS - 1)个周期
+    assert!(elapsed >= ((JOBS - 1) * attr.period) as isize);
+    println!("edf_test passed!");
+    0
+}
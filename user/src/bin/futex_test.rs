@@ -0,0 +1,51 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::AtomicU32;
+use user_lib::{exit, futex_wait, futex_wake, thread_create, waittid, yield_, FutexMutex};
+
+const THREADS: usize = 4;
+const ROUNDS: usize = 200;
+
+static LOCK: FutexMutex = FutexMutex::new();
+static mut COUNTER: usize = 0;
+
+fn worker(_arg: usize) -> ! {
+    for _ in 0..ROUNDS {
+        LOCK.lock();
+        // 在临界区内让出CPU，制造锁竞争
+        let value = unsafe { COUNTER };
+        yield_();
+        unsafe {
+            COUNTER = value + 1;
+        }
+        LOCK.unlock();
+    }
+    exit(0);
+    unreachable!();
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    // 值不匹配时不会阻塞，没有等待者时唤醒0个
+    let word = AtomicU32::new(1);
+    assert_eq!(futex_wait(&word, 0), -11);
+    assert_eq!(futex_wake(&word, 1), 0);
+    // 无竞争路径
+    LOCK.lock();
+    LOCK.unlock();
+
+    let mut tids = [0; THREADS];
+    for tid in tids.iter_mut() {
+        *tid = thread_create(worker as usize, 0);
+    }
+    for tid in tids.iter() {
+        assert_eq!(waittid(*tid as usize), 0);
+    }
+    assert_eq!(unsafe { COUNTER }, THREADS * ROUNDS);
+    println!("futex_test passed!");
+    0
+}
@@ -0,0 +1,73 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, mutex_blocking_create, mutex_create, mutex_lock, mutex_unlock, thread_create, waittid,
+    yield_, EPERM,
+};
+
+const THREADS: usize = 4;
+const ROUNDS: usize = 200;
+
+static mut COUNTER: usize = 0;
+
+/// 读出计数器后让出CPU再写回，没有互斥保护时一定会丢失更新
+fn critical_section() {
+    let value = unsafe { COUNTER };
+    yield_();
+    unsafe {
+        COUNTER = value + 1;
+    }
+}
+
+fn worker(mutex_id: usize) -> ! {
+    for _ in 0..ROUNDS {
+        mutex_lock(mutex_id);
+        critical_section();
+        mutex_unlock(mutex_id);
+    }
+    exit(0);
+    unreachable!();
+}
+
+fn unlock_foreign(mutex_id: usize) -> ! {
+    assert_eq!(mutex_unlock(mutex_id), EPERM);
+    exit(0);
+    unreachable!();
+}
+
+fn run_with(mutex_id: usize) {
+    unsafe {
+        COUNTER = 0;
+    }
+    let mut tids = [0; THREADS];
+    for tid in tids.iter_mut() {
+        *tid = thread_create(worker as usize, mutex_id);
+    }
+    for tid in tids.iter() {
+        assert_eq!(waittid(*tid as usize), 0);
+    }
+    assert_eq!(unsafe { COUNTER }, THREADS * ROUNDS);
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    run_with(mutex_create() as usize);
+    println!("mutex_test: spin mutex OK");
+    run_with(mutex_blocking_create() as usize);
+    println!("mutex_test: blocking mutex OK");
+    assert!(mutex_lock(100) < 0);
+    // 解锁未加锁或被其他线程持有的互斥锁
+    let mutex_id = mutex_blocking_create() as usize;
+    assert_eq!(mutex_unlock(mutex_id), EPERM);
+    mutex_lock(mutex_id);
+    let tid = thread_create(unlock_foreign as usize, mutex_id);
+    assert_eq!(waittid(tid as usize), 0);
+    assert_eq!(mutex_unlock(mutex_id), 0);
+    println!("mutex_test: bogus unlock rejected");
+    println!("mutex_test passed!");
+    0
+}
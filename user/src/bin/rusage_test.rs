@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{get_time, getrusage, times, yield_, Rusage, Tms, EINVAL, RUSAGE_SELF};
+
+/// 让出CPU的次数
+const YIELDS: usize = 10;
+
+#[no_mangle]
+fn main() -> i32 {
+    // 在用户态忙等待一段时间
+    let start = get_time();
+    while get_time() - start < 200 {}
+    for _ in 0..YIELDS {
+        yield_();
+    }
+    let mut tms = Tms::default();
+    let ticks = times(&mut tms);
+    assert!(ticks > 0);
+    let mut usage = Rusage::default();
+    assert_eq!(getrusage(RUSAGE_SELF, &mut usage), 0);
+    println!(
+        "rusage_test: utime = {}.{:06}s, stime = {}.{:06}s, nvcsw = {}, nivcsw = {}, tms = {:?}",
+        usage.ru_utime.sec,
+        usage.ru_utime.usec,
+        usage.ru_stime.sec,
+        usage.ru_stime.usec,
+        usage.ru_nvcsw,
+        usage.ru_nivcsw,
+        tms
+    );
+    assert!(usage.ru_nvcsw >= YIELDS);
+    // 忙等待的时间主要计入用户态
+    assert!(usage.ru_utime.sec * 1000 + usage.ru_utime.usec / 1000 >= 100);
+    // 无效的who返回-EINVAL
+    assert_eq!(getrusage(1, &mut usage), EINVAL);
+    println!("rusage_test passed!");
+    0
+}
@@ -0,0 +1,73 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    exit, getpid, kill, sigaction, sigmask, sigprocmask, thread_create, waittid, SignalAction,
+    SIGTERM, SIGUSR1, SIGUSR2, SIG_BLOCK, SIG_IGN, SIG_UNBLOCK,
+};
+
+/// 处理函数收到的信号编号之和
+static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+
+/// 处理函数直接返回，由用户库的restorer调用sigreturn回到被打断的位置
+fn handler(signum: usize) {
+    RECEIVED.fetch_add(signum, Ordering::SeqCst);
+}
+
+/// 访问空指针，触发SIGSEGV的默认动作，结束整个进程
+fn faulty(_arg: usize) -> ! {
+    unsafe {
+        (0 as *mut usize).write_volatile(0);
+    }
+    exit(0);
+    unreachable!();
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let pid = getpid() as usize;
+    let action = SignalAction {
+        handler: handler as usize,
+        ..SignalAction::default()
+    };
+    assert_eq!(sigaction(SIGUSR1, Some(&action), None), 0);
+    assert_eq!(sigaction(SIGUSR2, Some(&action), None), 0);
+    let mut old = SignalAction::default();
+    assert_eq!(sigaction(SIGUSR1, None, Some(&mut old)), 0);
+    assert_eq!(old.handler, handler as usize);
+
+    // 返回用户态前递送信号，处理函数返回后kill的返回值不受影响
+    assert_eq!(kill(pid, SIGUSR1), 0);
+    assert_eq!(RECEIVED.load(Ordering::SeqCst), SIGUSR1);
+    println!("handler ran for SIGUSR1");
+
+    // 被屏蔽的信号在解除屏蔽后才递送
+    sigprocmask(SIG_BLOCK, sigmask(SIGUSR2));
+    assert_eq!(kill(pid, SIGUSR2), 0);
+    assert_eq!(RECEIVED.load(Ordering::SeqCst), SIGUSR1);
+    let old_mask = sigprocmask(SIG_UNBLOCK, sigmask(SIGUSR2));
+    assert_eq!(old_mask as u32, sigmask(SIGUSR2));
+    assert_eq!(RECEIVED.load(Ordering::SeqCst), SIGUSR1 + SIGUSR2);
+    println!("blocked SIGUSR2 delivered after unblocking");
+
+    // 被忽略的信号不会结束进程
+    let ignore = SignalAction {
+        handler: SIG_IGN,
+        ..SignalAction::default()
+    };
+    assert_eq!(sigaction(SIGTERM, Some(&ignore), None), 0);
+    assert_eq!(kill(pid, SIGTERM), 0);
+    println!("SIGTERM ignored");
+
+    println!("signal test passed!");
+
+    // 页错误产生SIGSEGV，默认动作以-SIGSEGV结束整个进程，主线程不会从waittid返回
+    println!("expecting SIGSEGV to kill the whole process");
+    let tid = thread_create(faulty as usize, 0);
+    waittid(tid as usize);
+    panic!("SIGSEGV in a thread did not kill the process");
+}
@@ -0,0 +1,53 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{exit, get_time, thread_create, waittid};
+
+/// 线程个数，与qemu的-smp 4对应
+const THREADS: usize = 4;
+/// 每个线程忙等的时间（毫秒），期间不主动让出CPU
+const WORK_MS: isize = 500;
+
+/// 所有线程并发地对同一个计数器做原子累加
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+fn worker(_idx: usize) -> ! {
+    let start = get_time();
+    let mut rounds = 0;
+    while get_time() - start < WORK_MS {
+        COUNTER.fetch_add(1, Ordering::Relaxed);
+        rounds += 1;
+    }
+    exit(rounds as i32);
+    unreachable!();
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let start = get_time();
+    let mut tids = [0; THREADS];
+    for (idx, tid) in tids.iter_mut().enumerate() {
+        *tid = thread_create(worker as usize, idx);
+        assert!(*tid > 0);
+    }
+    let mut total = 0;
+    for tid in tids.iter() {
+        let rounds = waittid(*tid as usize);
+        assert!(rounds > 0);
+        total += rounds as usize;
+    }
+    let elapsed = get_time() - start;
+    // 原子累加不会丢失更新
+    assert_eq!(COUNTER.load(Ordering::Relaxed), total);
+    // 单核上各线程轮流运行，总时间约为THREADS * WORK_MS；多核上接近WORK_MS
+    println!(
+        "{} threads x {}ms busy finished in {}ms",
+        THREADS, WORK_MS, elapsed
+    );
+    println!("smp test passed!");
+    0
+}
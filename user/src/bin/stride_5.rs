@@ -0,0 +1,18 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::count_during;
+
+/// 在固定的时间窗口内计数，计数值与获得的CPU时间成正比
+const MAX_TIME: isize = 1000;
+
+#[no_mangle]
+fn main() -> i32 {
+    let prio = 5;
+    let count = count_during(prio, MAX_TIME);
+    println!("priority = {}, count = {}, count / priority = {}", prio, count, count / prio);
+    0
+}
@@ -0,0 +1,99 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    condvar_create, condvar_signal, condvar_wait, exit, mutex_blocking_create, mutex_lock,
+    mutex_unlock, semaphore_create, semaphore_down, semaphore_up, sleep, thread_create, waittid,
+};
+
+/// 环形缓冲区的大小和生产的元素个数
+const BUFFER_SIZE: usize = 4;
+const ITEMS: usize = 32;
+
+static mut BUFFER: [usize; BUFFER_SIZE] = [0; BUFFER_SIZE];
+static mut HEAD: usize = 0;
+static mut TAIL: usize = 0;
+
+/// 信号量id：空槽位数、已填充槽位数，以及保护缓冲区的互斥锁id
+static mut EMPTY: usize = 0;
+static mut FULL: usize = 0;
+static mut MUTEX: usize = 0;
+
+fn producer(_arg: usize) -> ! {
+    for item in 0..ITEMS {
+        unsafe {
+            semaphore_down(EMPTY);
+            mutex_lock(MUTEX);
+            BUFFER[TAIL % BUFFER_SIZE] = item;
+            TAIL += 1;
+            mutex_unlock(MUTEX);
+            semaphore_up(FULL);
+        }
+    }
+    exit(0);
+    unreachable!();
+}
+
+fn consumer(_arg: usize) -> ! {
+    let mut sum = 0;
+    for _ in 0..ITEMS {
+        unsafe {
+            semaphore_down(FULL);
+            mutex_lock(MUTEX);
+            sum += BUFFER[HEAD % BUFFER_SIZE];
+            HEAD += 1;
+            mutex_unlock(MUTEX);
+            semaphore_up(EMPTY);
+        }
+    }
+    exit(sum as i32);
+    unreachable!();
+}
+
+/// 条件变量测试：waiter等待READY被置位
+static mut READY: bool = false;
+static mut CONDVAR: usize = 0;
+
+fn waiter(_arg: usize) -> ! {
+    unsafe {
+        mutex_lock(MUTEX);
+        while !READY {
+            condvar_wait(CONDVAR, MUTEX);
+        }
+        mutex_unlock(MUTEX);
+    }
+    exit(1);
+    unreachable!();
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    unsafe {
+        EMPTY = semaphore_create(BUFFER_SIZE) as usize;
+        FULL = semaphore_create(0) as usize;
+        MUTEX = mutex_blocking_create() as usize;
+        CONDVAR = condvar_create() as usize;
+    }
+    let consumer_tid = thread_create(consumer as usize, 0);
+    let producer_tid = thread_create(producer as usize, 0);
+    assert_eq!(waittid(producer_tid as usize), 0);
+    assert_eq!(waittid(consumer_tid as usize), (ITEMS * (ITEMS - 1) / 2) as isize);
+    println!("sync_test: semaphore producer/consumer OK");
+
+    let waiter_tid = thread_create(waiter as usize, 0);
+    // 让waiter先进入等待
+    sleep(10);
+    unsafe {
+        mutex_lock(MUTEX);
+        READY = true;
+        condvar_signal(CONDVAR);
+        mutex_unlock(MUTEX);
+    }
+    assert_eq!(waittid(waiter_tid as usize), 1);
+    println!("sync_test: condvar OK");
+    println!("sync_test passed!");
+    0
+}
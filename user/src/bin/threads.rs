@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, gettid, thread_create, waittid, yield_, EINVAL, ESRCH};
+
+/// 线程个数
+const THREADS: usize = 3;
+/// 每个线程的累加次数
+const ROUNDS: usize = 1000;
+
+/// 所有线程共享同一个地址空间，各自累加自己的计数器
+static mut COUNTERS: [usize; THREADS] = [0; THREADS];
+
+fn worker(idx: usize) -> ! {
+    for i in 0..ROUNDS {
+        unsafe {
+            COUNTERS[idx] += 1;
+        }
+        if i % 100 == 0 {
+            yield_();
+        }
+    }
+    println!("thread {} (tid {}) done", idx, gettid());
+    exit(100 + idx as i32);
+    unreachable!();
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    assert_eq!(gettid(), 0);
+    let mut tids = [0; THREADS];
+    for (idx, tid) in tids.iter_mut().enumerate() {
+        *tid = thread_create(worker as usize, idx);
+        assert!(*tid > 0);
+    }
+    for (idx, tid) in tids.iter().enumerate() {
+        assert_eq!(waittid(*tid as usize), 100 + idx as isize);
+    }
+    // 线程已被回收，不能再次等待；也不能等待自身
+    assert_eq!(waittid(tids[0] as usize), ESRCH);
+    assert_eq!(waittid(0), EINVAL);
+    for idx in 0..THREADS {
+        assert_eq!(unsafe { COUNTERS[idx] }, ROUNDS);
+    }
+    println!("threads test passed!");
+    0
+}
@@ -0,0 +1,67 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use user_lib::{
+    exit, get_time, sbrk, sigaction, sleep, thread_create, waittid, yield_, SignalAction, SIGSEGV,
+};
+
+const PAGE_SIZE: usize = 0x1000;
+/// 读线程最长运行的时间（毫秒），超时说明它一直在通过残留的TLB表项访问已解除映射的页
+const SPIN_MS: isize = 2000;
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+static PAGE: AtomicUsize = AtomicUsize::new(0);
+
+/// SIGSEGV的默认动作会结束整个进程，这里只结束触发页错误的读线程
+fn on_segv(signum: usize) {
+    exit(-(signum as i32));
+}
+
+/// 在另一个hart上不断读取堆页，页被解除映射后应当触发页错误而被SIGSEGV结束
+fn reader(_arg: usize) -> ! {
+    let page = PAGE.load(Ordering::Acquire) as *const usize;
+    let start = get_time();
+    STARTED.store(true, Ordering::Release);
+    while get_time() - start < SPIN_MS {
+        for i in 0..PAGE_SIZE / core::mem::size_of::<usize>() {
+            unsafe {
+                assert_eq!(page.add(i).read_volatile(), i);
+            }
+        }
+    }
+    exit(0);
+    unreachable!();
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let page = sbrk(PAGE_SIZE as i32) as usize;
+    for i in 0..PAGE_SIZE / core::mem::size_of::<usize>() {
+        unsafe {
+            (page as *mut usize).add(i).write_volatile(i);
+        }
+    }
+    PAGE.store(page, Ordering::Release);
+    let action = SignalAction {
+        handler: on_segv as usize,
+        ..SignalAction::default()
+    };
+    assert_eq!(sigaction(SIGSEGV, Some(&action), None), 0);
+    let tid = thread_create(reader as usize, 0);
+    assert!(tid > 0);
+    while !STARTED.load(Ordering::Acquire) {
+        yield_();
+    }
+    // 让读线程在其他hart上把该页的表项装入TLB
+    sleep(100);
+    // 缩减堆，解除该页的映射并刷新所有hart的TLB
+    assert_eq!(sbrk(-(PAGE_SIZE as i32)) as usize, page + PAGE_SIZE);
+    // 读线程因页错误收到SIGSEGV退出
+    assert_eq!(waittid(tid as usize), -(SIGSEGV as isize));
+    println!("tlb shootdown test passed!");
+    0
+}
@@ -7,13 +7,75 @@ pub mod console;
 mod lang_items;
 mod syscall;
 
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// 辅助向量（auxv）中的类型
+pub const AT_NULL: usize = 0;
+pub const AT_PHDR: usize = 3;
+pub const AT_PHENT: usize = 4;
+pub const AT_PHNUM: usize = 5;
+pub const AT_PAGESZ: usize = 6;
+pub const AT_BASE: usize = 7;
+pub const AT_ENTRY: usize = 9;
+
+/// 内核在初始栈上构造的argv数组及其长度
+static mut ARGC: usize = 0;
+static mut ARGV: *const usize = core::ptr::null();
+/// auxv数组的起始地址，位于envp数组的NULL之后
+static mut AUXV: *const usize = core::ptr::null();
+
+/// 内核按System V约定在栈上放置argc、argv、envp、auxv，并通过a0、a1传递argc和argv
 #[no_mangle]
 #[link_section = ".text.entry"]
-pub extern "C" fn _start() -> ! {
+pub extern "C" fn _start(argc: usize, argv: usize) -> ! {
+    unsafe {
+        ARGC = argc;
+        ARGV = argv as *const usize;
+        // 跳过argv数组及其NULL，再跳过以NULL结尾的envp数组
+        let mut envp = ARGV.add(argc + 1);
+        while *envp != 0 {
+            envp = envp.add(1);
+        }
+        AUXV = envp.add(1);
+    }
     exit(main());
     panic!("unreachable after sys_exit!");
 }
 
+/// 获取命令行参数的个数
+pub fn argc() -> usize {
+    unsafe { ARGC }
+}
+
+/// 获取第i个命令行参数
+pub fn argv(i: usize) -> Option<&'static str> {
+    if i >= argc() {
+        return None;
+    }
+    unsafe {
+        let start = *ARGV.add(i) as *const u8;
+        let mut len = 0;
+        while *start.add(len) != 0 {
+            len += 1;
+        }
+        core::str::from_utf8(core::slice::from_raw_parts(start, len)).ok()
+    }
+}
+
+/// 在辅助向量中查找类型为key的值
+pub fn getauxval(key: usize) -> Option<usize> {
+    unsafe {
+        let mut p = AUXV;
+        while *p != AT_NULL {
+            if *p == key {
+                return Some(*p.add(1));
+            }
+            p = p.add(2);
+        }
+    }
+    None
+}
+
 #[linkage = "weak"]
 #[no_mangle]
 fn main() -> i32 {
@@ -22,19 +84,313 @@ fn main() -> i32 {
 
 use syscall::*;
 
+/// 进程时间，单位为时钟滴答（每秒100个）
+#[repr(C)]
+#[derive(Default, Debug)]
+pub struct Tms {
+    pub tms_utime: usize,
+    pub tms_stime: usize,
+    pub tms_cutime: usize,
+    pub tms_cstime: usize,
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Clone, Copy)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize,
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Clone, Copy)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+
+/// 资源使用情况
+#[repr(C)]
+#[derive(Default, Debug)]
+pub struct Rusage {
+    pub ru_utime: TimeVal,
+    pub ru_stime: TimeVal,
+    /// 主动让出CPU的次数
+    pub ru_nvcsw: usize,
+    /// 被抢占的次数
+    pub ru_nivcsw: usize,
+}
+
+pub const RUSAGE_SELF: isize = 0;
+
+/// 实时任务的调度参数，时间单位为毫秒
+#[repr(C)]
+#[derive(Default, Debug)]
+pub struct SchedAttr {
+    pub runtime: usize,
+    pub deadline: usize,
+    pub period: usize,
+    /// 错过截止期限的次数
+    pub misses: usize,
+}
+
+pub const SIGINT: usize = 2;
+pub const SIGILL: usize = 4;
+pub const SIGABRT: usize = 6;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGTERM: usize = 15;
+pub const SIGCHLD: usize = 17;
+
+/// 默认处理动作，大多数信号的默认动作是以-signum结束进程
+pub const SIG_DFL: usize = 0;
+/// 忽略该信号
+pub const SIG_IGN: usize = 1;
+
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+/// 信号集中编号为signum的位
+pub const fn sigmask(signum: usize) -> u32 {
+    1 << signum
+}
+
+/// 信号处理动作，与内核中的布局一致
+#[repr(C)]
+#[derive(Default, Debug, Clone, Copy)]
+pub struct SignalAction {
+    /// 处理函数地址，或SIG_DFL、SIG_IGN
+    pub handler: usize,
+    /// 处理函数执行期间额外屏蔽的信号
+    pub mask: u32,
+    /// 处理函数返回到的地址，由sigaction填写为调用sigreturn的restorer
+    pub restorer: usize,
+}
+
 pub fn write(fd: usize, buf: &[u8]) -> isize {
     sys_write(fd, buf)
 }
 pub fn exit(exit_code: i32) -> isize {
     sys_exit(exit_code)
 }
+pub fn sleep(ms: usize) -> isize {
+    sys_sleep(ms)
+}
 pub fn yield_() -> isize {
     sys_yield()
 }
+pub fn set_priority(prio: isize) -> isize {
+    sys_set_priority(prio)
+}
+/// 设置优先级为prio后在ms毫秒内自旋计数，计数值与获得的CPU时间成正比，用于比较各任务的CPU份额
+pub fn count_during(prio: isize, ms: isize) -> isize {
+    let start_time = get_time();
+    let mut acc = 0;
+    set_priority(prio);
+    loop {
+        spin_delay();
+        acc += 1;
+        if acc % 400 == 0 {
+            let time = get_time() - start_time;
+            if time > ms {
+                return acc;
+            }
+        }
+    }
+}
+fn spin_delay() {
+    let mut j = true;
+    for _ in 0..10 {
+        j = !j;
+    }
+}
+/// 开机以来经过的毫秒数
 pub fn get_time() -> isize {
-    sys_get_time()
+    let mut time = TimeVal::default();
+    match sys_get_time(&mut time as *mut TimeVal, 0) {
+        0 => (time.sec * 1000 + time.usec / 1000) as isize,
+        _ => -1,
+    }
+}
+/// 开机以来经过的微秒数
+pub fn get_time_us() -> isize {
+    let mut time = TimeVal::default();
+    match sys_get_time(&mut time as *mut TimeVal, 0) {
+        0 => (time.sec * 1_000_000 + time.usec) as isize,
+        _ => -1,
+    }
+}
+pub fn clock_gettime(clock_id: usize, tp: &mut TimeSpec) -> isize {
+    sys_clock_gettime(clock_id, tp as *mut TimeSpec)
+}
+
+pub fn times(tms: &mut Tms) -> isize {
+    sys_times(tms as *mut Tms)
+}
+pub fn getrusage(who: isize, usage: &mut Rusage) -> isize {
+    sys_getrusage(who, usage as *mut Rusage)
 }
 
 pub fn sbrk(size: i32) -> isize {
     sys_sbrk(size)
 }
+
+pub fn getpid() -> isize {
+    sys_getpid()
+}
+
+/// 向进程pid发送信号signum；进程正在阻塞时要等它被唤醒后才会处理，致命的信号则立即结束它
+pub fn kill(pid: usize, signum: usize) -> isize {
+    sys_kill(pid, signum)
+}
+/// 信号处理函数返回到这里，由sigreturn回到被打断的位置
+fn sigreturn_restorer() -> ! {
+    sys_sigreturn();
+    unreachable!();
+}
+/// 设置信号处理动作，old_action非空时返回原来的动作。
+/// 处理函数以信号编号为参数，可以直接返回，之后自动调用sigreturn回到被打断的位置
+pub fn sigaction(
+    signum: usize,
+    action: Option<&SignalAction>,
+    old_action: Option<&mut SignalAction>,
+) -> isize {
+    let action = action.map(|a| SignalAction {
+        restorer: sigreturn_restorer as usize,
+        ..*a
+    });
+    sys_sigaction(
+        signum,
+        action.as_ref().map_or(core::ptr::null(), |a| a as *const SignalAction),
+        old_action.map_or(core::ptr::null_mut(), |a| a as *mut SignalAction),
+    )
+}
+/// 按how修改当前线程的屏蔽信号集，返回修改前的屏蔽信号集
+pub fn sigprocmask(how: usize, set: u32) -> isize {
+    sys_sigprocmask(how, set)
+}
+/// 从信号处理函数返回
+pub fn sigreturn() -> isize {
+    sys_sigreturn()
+}
+
+/// 创建一个线程，从entry开始执行并以arg作为第一个参数；线程函数不能返回，需调用exit结束
+pub fn thread_create(entry: usize, arg: usize) -> isize {
+    sys_thread_create(entry, arg)
+}
+pub fn gettid() -> isize {
+    sys_gettid()
+}
+/// 等待线程tid退出，返回其退出码；等待自身时返回EINVAL，线程不存在或已被回收时返回ESRCH
+pub fn waittid(tid: usize) -> isize {
+    sys_waittid(tid)
+}
+
+pub const FUTEX_WAIT: usize = 0;
+pub const FUTEX_WAKE: usize = 1;
+
+/// 若*addr仍等于val则阻塞，直到被futex_wake唤醒；值已改变时立即返回-11（EAGAIN）
+pub fn futex_wait(addr: &AtomicU32, val: u32) -> isize {
+    sys_futex(addr as *const AtomicU32 as *const u32, FUTEX_WAIT, val as usize)
+}
+/// 唤醒最多count个在addr上等待的线程，返回唤醒的个数
+pub fn futex_wake(addr: &AtomicU32, count: usize) -> isize {
+    sys_futex(addr as *const AtomicU32 as *const u32, FUTEX_WAKE, count)
+}
+
+/// 基于futex的用户态互斥锁：无竞争时只做一次原子操作，不进入内核
+///
+/// state：0为未加锁，1为已加锁且无等待者，2为已加锁且可能有等待者
+pub struct FutexMutex {
+    state: AtomicU32,
+}
+
+impl FutexMutex {
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(0),
+        }
+    }
+    pub fn lock(&self) {
+        if self
+            .state
+            .compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            return;
+        }
+        // 有竞争：标记为可能有等待者，锁仍被占用时在内核中等待
+        while self.state.swap(2, Ordering::Acquire) != 0 {
+            futex_wait(&self.state, 2);
+        }
+    }
+    pub fn unlock(&self) {
+        if self.state.swap(0, Ordering::Release) == 2 {
+            futex_wake(&self.state, 1);
+        }
+    }
+}
+
+/// 操作不被允许时返回的错误码，例如解锁不属于自己的互斥锁
+pub const EPERM: isize = -1;
+/// 指定的进程或线程不存在
+pub const ESRCH: isize = -3;
+/// 参数无效时返回的错误码
+pub const EINVAL: isize = -22;
+/// 死锁检测拒绝申请时返回的错误码
+pub const EDEADLK: isize = -35;
+
+/// 开启或关闭当前进程的死锁检测，开启后可能导致死锁的加锁或P操作返回EDEADLK
+pub fn enable_deadlock_detect(enabled: bool) -> isize {
+    sys_enable_deadlock_detect(enabled as usize)
+}
+pub fn mutex_create() -> isize {
+    sys_mutex_create(false)
+}
+pub fn mutex_blocking_create() -> isize {
+    sys_mutex_create(true)
+}
+pub fn mutex_lock(mutex_id: usize) -> isize {
+    sys_mutex_lock(mutex_id)
+}
+pub fn mutex_unlock(mutex_id: usize) -> isize {
+    sys_mutex_unlock(mutex_id)
+}
+pub fn semaphore_create(res_count: usize) -> isize {
+    sys_semaphore_create(res_count)
+}
+pub fn semaphore_up(sem_id: usize) -> isize {
+    sys_semaphore_up(sem_id)
+}
+pub fn semaphore_down(sem_id: usize) -> isize {
+    sys_semaphore_down(sem_id)
+}
+pub fn condvar_create() -> isize {
+    sys_condvar_create()
+}
+pub fn condvar_signal(condvar_id: usize) -> isize {
+    sys_condvar_signal(condvar_id)
+}
+pub fn condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    sys_condvar_wait(condvar_id, mutex_id)
+}
+
+pub fn nice(increment: isize) -> isize {
+    sys_nice(increment)
+}
+
+/// 成为EDF实时任务：每period毫秒最多运行runtime毫秒，需在deadline毫秒内完成，yield_表示本周期的作业完成。
+/// 参数不合法或未通过准入控制时返回EINVAL
+pub fn sched_setattr(runtime: usize, deadline: usize, period: usize) -> isize {
+    sys_sched_setattr(runtime, deadline, period)
+}
+
+/// 获取当前任务的实时参数，当前任务不是实时任务时返回EINVAL
+pub fn sched_getattr(attr: &mut SchedAttr) -> isize {
+    sys_sched_getattr(attr as *mut SchedAttr)
+}